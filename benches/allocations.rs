@@ -0,0 +1,109 @@
+//! Allocations per tick of built-in nodes,
+//! task created again each tick vs task kept by a TreeInstance.
+//!
+//! cargo bench --bench allocations
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicUsize, Ordering},
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+use bhv_async::prelude::*;
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const TICKS: usize = 200;
+
+fn instant() -> Composite {
+    Wait::new(Duration::ZERO).into()
+}
+
+fn run<F: Future<Output = RunStatus> + Unpin>(task: &mut F) -> RunStatus {
+    let mut cx = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(status) = Pin::new(&mut *task).poll(&mut cx) {
+            return status;
+        }
+    }
+}
+
+/// Mean allocations of one tick
+fn measure(mut tick: impl FnMut()) -> f64 {
+    // first run fill buffers, slots,...
+    tick();
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..TICKS {
+        tick();
+    }
+    (ALLOCATIONS.load(Ordering::Relaxed) - before) as f64 / TICKS as f64
+}
+
+fn main() {
+    let trees: Vec<(&str, Composite)> = vec![
+        ("Sequence x8", Sequence::new(vec![instant(); 8]).into()),
+        (
+            "PrioritySelector x4",
+            PrioritySelector::new([
+                Inverter::new(instant()).into(),
+                Inverter::new(instant()).into(),
+                Inverter::new(instant()).into(),
+                instant(),
+            ])
+            .into(),
+        ),
+        (
+            "Timeout/Decorator/Inverter",
+            Timeout::new(
+                Duration::from_secs(1),
+                Decorator::new(|| true, Inverter::new(Inverter::new(instant()))),
+            )
+            .into(),
+        ),
+        (
+            "WeightedSelector",
+            WeightedSelector::with_seed(vec![(1.0, instant()), (2.0, instant())], 7).into(),
+        ),
+    ];
+
+    let mut results = Vec::new();
+    for (name, root) in &trees {
+        let fresh = measure(|| {
+            let mut task = (root.task_production)();
+            run(&mut task);
+        });
+        let mut instance = TreeDefinition::new(root.clone()).instantiate();
+        let reused = measure(|| {
+            run(&mut instance);
+        });
+        results.push((name, fresh, reused));
+    }
+
+    println!();
+    println!("{:<28} {:>12} {:>12}", "tree", "fresh/tick", "reused/tick");
+    for (name, fresh, reused) in results {
+        println!("{name:<28} {fresh:>12.1} {reused:>12.1}");
+        assert!(reused < fresh);
+        // logs included, only shared buffers (timer queue) grow from time to time
+        assert!(reused < 0.05, "{name} allocate each tick when reused");
+    }
+}
@@ -0,0 +1,318 @@
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{
+    bracketed, parenthesized, parse::Parse, punctuated::Punctuated, spanned::Spanned, Expr,
+    ExprLit, Ident, Lit, LitStr, Token,
+};
+
+/// Attributes of a node: `#[name = "attack", tags("combat"), timeout = "2s", retry = 3]`
+/// - `name`, `description`, `tags(...)`: stored in Composite
+/// - `timeout`, `retry`, `repeat`, `invert`, `condition`, `interrupt`: wrap node
+///   in a decorator, first one is innermost.
+///   `retry = 3` run node again up to 3 times after a failure (4 runs at most),
+///   `repeat = 3` run it 3 times at most
+/// - `cfg(...)`: child of group macro exist only with this cfg
+/// - `seed = 42` or `rng = fastrand::Rng::new()`: generator of random group
+#[derive(Default)]
+pub struct NodeAttrs {
+    cfg: Vec<TokenStream2>,
+    rng: Option<(Ident, GroupRng)>,
+    name: Option<Expr>,
+    description: Option<Expr>,
+    tags: Vec<LitStr>,
+    wrappers: Vec<Wrapper>,
+}
+
+enum Wrapper {
+    Timeout(TokenStream2),
+    Retry(Expr),
+    Repeat(Expr),
+    Invert,
+    Condition(Expr),
+    Interrupt(Expr),
+}
+
+/// Generator of RandomSequence, RandomSelector and WeightedSelector
+pub enum GroupRng {
+    Seed(Expr),
+    Rng(Expr),
+}
+
+const KEYS: &str =
+    "cfg, name, description, tags, timeout, retry, repeat, invert, condition, interrupt, seed, rng";
+
+impl NodeAttrs {
+    /// `#[...]` attributes, if any
+    pub fn parse_outer(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let mut attrs = Self::default();
+        attrs.parse_more(input)?;
+        Ok(attrs)
+    }
+
+    /// Add following `#[...]` attributes, if any
+    pub fn parse_more(&mut self, input: syn::parse::ParseStream) -> syn::Result<()> {
+        while input.peek(Token![#]) && !input.peek2(Token![!]) {
+            input.parse::<Token![#]>()?;
+            self.parse_bracketed(input)?;
+        }
+        Ok(())
+    }
+
+    /// `#![...]` attributes of enclosing group, if any
+    pub fn parse_inner(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let mut attrs = Self::default();
+        while input.peek(Token![#]) && input.peek2(Token![!]) {
+            input.parse::<Token![#]>()?;
+            input.parse::<Token![!]>()?;
+            attrs.parse_bracketed(input)?;
+        }
+        Ok(attrs)
+    }
+
+    /// `#[cfg(...)]` to put on statement adding child
+    pub fn cfg(&self) -> TokenStream2 {
+        let cfg = &self.cfg;
+        quote!(#(#[cfg(#cfg)])*)
+    }
+
+    pub fn has_cfg(&self) -> bool {
+        !self.cfg.is_empty()
+    }
+
+    /// cfg is only for children of group macros
+    pub fn reject_cfg(&self) -> syn::Result<()> {
+        match self.cfg.first() {
+            Some(cfg) => Err(syn::Error::new(
+                cfg.span(),
+                "`cfg` can only be put on a child of group macro",
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Generator given by `seed` or `rng`, used by random group
+    pub fn rng(&self) -> Option<&GroupRng> {
+        self.rng.as_ref().map(|(_, rng)| rng)
+    }
+
+    /// seed and rng are only for random groups
+    pub fn reject_rng(&self) -> syn::Result<()> {
+        match &self.rng {
+            Some((key, _)) => Err(syn::Error::new(
+                key.span(),
+                format!("`{key}` can only be put on random group: `RandomSelector! {{ #![{key} = ...] ... }}`"),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// No attribute changing node (cfg and generator excluded)
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none()
+            && self.description.is_none()
+            && self.tags.is_empty()
+            && self.wrappers.is_empty()
+    }
+
+    fn parse_bracketed(&mut self, input: syn::parse::ParseStream) -> syn::Result<()> {
+        let content;
+        bracketed!(content in input);
+        let attrs = Punctuated::<Attr, Token![,]>::parse_terminated(&content)?;
+        for attr in attrs {
+            self.add(attr)?;
+        }
+        Ok(())
+    }
+
+    fn add(&mut self, Attr { key, value }: Attr) -> syn::Result<()> {
+        let expected = |what: &str| syn::Error::new(key.span(), format!("expected `{key}{what}`"));
+        let value_of = |value: AttrValue| match value {
+            AttrValue::Value(expr) => Ok(expr),
+            _ => Err(expected(" = ...")),
+        };
+        match key.to_string().as_str() {
+            "cfg" => {
+                let AttrValue::Tokens(cfg) = value else {
+                    return Err(expected("(...)"));
+                };
+                self.cfg.push(cfg);
+            }
+            "name" => {
+                if self.name.is_some() {
+                    return Err(syn::Error::new(key.span(), "duplicate attribute `name`"));
+                }
+                self.name = Some(value_of(value)?);
+            }
+            "description" => self.description = Some(value_of(value)?),
+            "tags" => {
+                let AttrValue::List(tags) = value else {
+                    return Err(expected("(\"tag\", ...)"));
+                };
+                for tag in tags {
+                    match tag {
+                        Expr::Lit(ExprLit {
+                            lit: Lit::Str(tag), ..
+                        }) => self.tags.push(tag),
+                        tag => {
+                            return Err(syn::Error::new(tag.span(), "tag must be a string literal"))
+                        }
+                    }
+                }
+            }
+            "timeout" => {
+                let duration = duration(&value_of(value)?)?;
+                self.wrappers.push(Wrapper::Timeout(duration));
+            }
+            "retry" => self.wrappers.push(Wrapper::Retry(value_of(value)?)),
+            "repeat" => self.wrappers.push(Wrapper::Repeat(value_of(value)?)),
+            "condition" => self.wrappers.push(Wrapper::Condition(value_of(value)?)),
+            "interrupt" => self.wrappers.push(Wrapper::Interrupt(value_of(value)?)),
+            "seed" | "rng" => {
+                if self.rng.is_some() {
+                    return Err(syn::Error::new(
+                        key.span(),
+                        "duplicate generator, use one of `seed` or `rng`",
+                    ));
+                }
+                let value = value_of(value)?;
+                let rng = match key == "seed" {
+                    true => GroupRng::Seed(value),
+                    false => GroupRng::Rng(value),
+                };
+                self.rng = Some((key, rng));
+            }
+            "invert" => {
+                let AttrValue::Flag = value else {
+                    return Err(expected(""));
+                };
+                self.wrappers.push(Wrapper::Invert);
+            }
+            _ => {
+                return Err(syn::Error::new(
+                    key.span(),
+                    format!("unknown attribute `{key}`, expected one of: {KEYS}"),
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    /// Composite expression `node` with metadata, wrapped in decorators
+    pub fn apply(&self, node: TokenStream2) -> TokenStream2 {
+        let mut node = quote! {
+            ::core::convert::Into::<::bhv_async::composite::Composite>::into(#node)
+        };
+        if let Some(name) = &self.name {
+            node = quote!(#node.with_name(#name));
+        }
+        if let Some(description) = &self.description {
+            node = quote!(#node.with_description(#description));
+        }
+        if !self.tags.is_empty() {
+            let tags = &self.tags;
+            node = quote!(#node.with_tags(&[#(#tags),*]));
+        }
+
+        let path = quote!(::bhv_async::common_behaviors);
+        for wrapper in &self.wrappers {
+            let wrapped = match wrapper {
+                Wrapper::Timeout(duration) => quote!(#path::Timeout::new(#duration, #node)),
+                // first run is not a retry
+                Wrapper::Retry(count) => {
+                    quote!(#path::UntilSuccess::new(#node).with_max_runs((#count) + 1))
+                }
+                Wrapper::Repeat(count) => {
+                    quote!(#path::UntilFailure::new(#node).with_max_runs(#count))
+                }
+                Wrapper::Invert => quote!(#path::Inverter::new(#node)),
+                Wrapper::Condition(condition) => quote!(#path::Decorator::new(#condition, #node)),
+                Wrapper::Interrupt(condition) => {
+                    quote!(#path::InterruptAction::new(#condition, #node))
+                }
+            };
+            node = quote! {
+                ::core::convert::Into::<::bhv_async::composite::Composite>::into(#wrapped)
+            };
+        }
+        node
+    }
+}
+
+struct Attr {
+    key: Ident,
+    value: AttrValue,
+}
+
+enum AttrValue {
+    /// `invert`
+    Flag,
+    /// `retry = 3`
+    Value(Expr),
+    /// `tags("a", "b")`
+    List(Vec<Expr>),
+    /// `cfg(feature = "debug")`, kept as is
+    Tokens(TokenStream2),
+}
+
+impl Parse for Attr {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let key = input.parse::<Ident>()?;
+        let value = if input.peek(Token![=]) {
+            input.parse::<Token![=]>()?;
+            AttrValue::Value(input.parse()?)
+        } else if input.peek(syn::token::Paren) && key == "cfg" {
+            let content;
+            parenthesized!(content in input);
+            AttrValue::Tokens(content.parse()?)
+        } else if input.peek(syn::token::Paren) {
+            let content;
+            parenthesized!(content in input);
+            let list = Punctuated::<Expr, Token![,]>::parse_terminated(&content)?;
+            AttrValue::List(list.into_iter().collect())
+        } else {
+            AttrValue::Flag
+        };
+        Ok(Self { key, value })
+    }
+}
+
+/// Duration of attribute:
+/// - `"2s"`, `"500ms"`, `"1.5m"`, `"1h"`
+/// - number of seconds
+/// - any expression giving a Duration
+pub fn duration(value: &Expr) -> syn::Result<TokenStream2> {
+    let Expr::Lit(ExprLit { lit, .. }) = value else {
+        return Ok(quote!(#value));
+    };
+    match lit {
+        Lit::Int(lit) => Ok(quote!(::std::time::Duration::from_secs(#lit))),
+        Lit::Float(lit) => Ok(quote!(::std::time::Duration::from_secs_f64(#lit))),
+        Lit::Str(lit) => duration_str(&lit.value(), lit.span()),
+        lit => Err(syn::Error::new(
+            lit.span(),
+            "expected duration: \"2s\", \"500ms\", seconds or a Duration",
+        )),
+    }
+}
+
+fn duration_str(text: &str, span: Span) -> syn::Result<TokenStream2> {
+    let error = || {
+        syn::Error::new(
+            span,
+            format!("invalid duration {text:?}, expected number with unit: ms, s, m or h"),
+        )
+    };
+    let split = text
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .ok_or_else(error)?;
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number.parse().map_err(|_| error())?;
+    let seconds = match unit.trim() {
+        "ms" => number / 1000.0,
+        "s" => number,
+        "m" => number * 60.0,
+        "h" => number * 3600.0,
+        _ => return Err(error()),
+    };
+    Ok(quote!(::std::time::Duration::from_secs_f64(#seconds)))
+}
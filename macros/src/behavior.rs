@@ -0,0 +1,145 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse::Parse, spanned::Spanned, FnArg, GenericParam, ItemFn, LitStr, Pat, ReturnType, Token,
+    Type,
+};
+
+/// `#[behavior]` or `#[behavior(name = "custom")]`
+pub struct BehaviorArgs {
+    name: Option<LitStr>,
+}
+
+impl Parse for BehaviorArgs {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        if input.is_empty() {
+            return Ok(Self { name: None });
+        }
+        let key = input.parse::<syn::Ident>()?;
+        if key != "name" {
+            return Err(syn::Error::new(
+                key.span(),
+                "unknown argument, expected `name = \"...\"`",
+            ));
+        }
+        input.parse::<Token![=]>()?;
+        let name = input.parse()?;
+        if !input.is_empty() {
+            return Err(input.error("unexpected token after name"));
+        }
+        Ok(Self { name: Some(name) })
+    }
+}
+
+/// async fn turned into constructor of its node
+pub struct BehaviorFn {
+    item: ItemFn,
+}
+
+impl Parse for BehaviorFn {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let item = input.parse::<ItemFn>()?;
+        let sig = &item.sig;
+        if sig.asyncness.is_none() {
+            return Err(syn::Error::new(
+                sig.fn_token.span(),
+                "behavior must be an async fn: `async fn patrol() -> RunStatus`",
+            ));
+        }
+        if matches!(sig.output, ReturnType::Default) {
+            return Err(syn::Error::new(
+                sig.ident.span(),
+                "behavior must return RunStatus: `-> RunStatus`",
+            ));
+        }
+        if let Some(param) = sig
+            .generics
+            .params
+            .iter()
+            .find(|param| matches!(param, GenericParam::Lifetime(_)))
+        {
+            return Err(syn::Error::new(
+                param.span(),
+                "behavior can not borrow, its parameters are cloned into each task",
+            ));
+        }
+        for input in &sig.inputs {
+            let FnArg::Typed(typed) = input else {
+                return Err(syn::Error::new(
+                    input.span(),
+                    "behavior can not take self, use a free function",
+                ));
+            };
+            if !matches!(typed.pat.as_ref(), Pat::Ident(_)) {
+                return Err(syn::Error::new(
+                    typed.pat.span(),
+                    "behavior parameter must be a name: `target: Vec2`",
+                ));
+            }
+            if matches!(typed.ty.as_ref(), Type::Reference(_) | Type::ImplTrait(_)) {
+                return Err(syn::Error::new(
+                    typed.ty.span(),
+                    "behavior parameter must be an owned Clone type, it is cloned into each task",
+                ));
+            }
+        }
+        Ok(Self { item })
+    }
+}
+
+impl BehaviorFn {
+    pub fn parse_token(&self, args: BehaviorArgs) -> TokenStream2 {
+        let ItemFn {
+            attrs,
+            vis,
+            sig,
+            block,
+        } = &self.item;
+        let ident = &sig.ident;
+        let name = args
+            .name
+            .unwrap_or_else(|| LitStr::new(&ident.to_string(), ident.span()));
+        let (impl_generics, ty_generics, where_clause) = sig.generics.split_for_impl();
+        let turbofish = ty_generics.as_turbofish();
+
+        let params: Vec<_> = sig
+            .inputs
+            .iter()
+            .filter_map(|input| match input {
+                FnArg::Typed(typed) => Some(typed),
+                FnArg::Receiver(_) => None,
+            })
+            .collect();
+        // checked by parse
+        let names: Vec<_> = params
+            .iter()
+            .map(|typed| match typed.pat.as_ref() {
+                Pat::Ident(pat) => &pat.ident,
+                _ => unreachable!(),
+            })
+            .collect();
+        let types = params.iter().map(|typed| &typed.ty);
+
+        // original fn, its name is shadowed inside constructor
+        let task_fn = ItemFn {
+            attrs: vec![],
+            vis: syn::Visibility::Inherited,
+            sig: sig.clone(),
+            block: block.clone(),
+        };
+        quote! {
+            #(#attrs)*
+            #[track_caller]
+            #vis fn #ident #impl_generics (#(#names: #types),*) -> ::bhv_async::composite::Composite
+            #where_clause
+            {
+                #task_fn
+                ::bhv_async::composite::Composite::new(#name, move || {
+                    #(let #names = ::core::clone::Clone::clone(&#names);)*
+                    ::std::boxed::Box::pin(#ident #turbofish (#(#names),*))
+                        as ::bhv_async::composite::BoxAction
+                })
+            }
+        }
+    }
+}
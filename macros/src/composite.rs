@@ -1,100 +1,247 @@
-use proc_macro2::{Literal, TokenStream as TokenStream2};
+use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
-use syn::{parse::Parse, Expr, ExprClosure, Stmt, Token};
 
-#[derive(Debug)]
+use crate::attributes::NodeAttrs;
+use syn::{
+    braced, parse::Parse, parse_quote, spanned::Spanned, visit_mut::VisitMut, Block, Expr,
+    ExprClosure, Ident, Lit, LitStr, Stmt, Token,
+};
+
 pub struct ActionData {
-    action_name: Option<Literal>,
-    closure: ExprClosure,
+    attrs: NodeAttrs,
+    action_name: Option<LitStr>,
+    body: ActionBody,
+}
+
+enum ActionBody {
+    /// `|| async { ... }` or `move || { setup; return async move { ... } }`
+    Closure(ExprClosure),
+    /// `something().await; RunStatus::Success`,
+    /// run inside a new `async move` block for each task.
+    /// Captures (`clone a, b => { ... }`) are cloned for each task.
+    Block {
+        captures: Vec<Ident>,
+        stmts: Vec<Stmt>,
+    },
 }
 
 impl Parse for ActionData {
     fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
-        let action_name = input.parse::<Literal>().ok();
-        if action_name.is_some() {
+        let attrs = NodeAttrs::parse_outer(input)?;
+        attrs.reject_cfg()?;
+        attrs.reject_rng()?;
+        let action_name = if input.peek(LitStr) {
+            let name = input.parse::<LitStr>()?;
             input.parse::<Token![,]>()?;
+            Some(name)
+        } else if input.peek(Lit) {
+            return Err(input.error(
+                "action name must be a string literal, e.g. `Action! { \"patrol\", || async { ... } }`",
+            ));
+        } else {
+            None
+        };
+
+        if input.peek(Token![|]) || input.peek(Token![||]) || input.peek(Token![move]) {
+            let mut closure: ExprClosure = input.parse()?;
+            check_body(closure.body.as_ref())?;
+            box_returns(&mut closure)?;
+            return Ok(Self {
+                attrs,
+                action_name,
+                body: ActionBody::Closure(closure),
+            });
         }
-        let closure: ExprClosure = input.parse()?;
 
+        let captures = parse_captures(input)?;
+        let stmts = if !captures.is_empty() && input.peek(syn::token::Brace) {
+            let content;
+            braced!(content in input);
+            let stmts = content.call(Block::parse_within)?;
+            if !input.is_empty() {
+                return Err(input.error("unexpected token after action body"));
+            }
+            stmts
+        } else {
+            input.call(Block::parse_within)?
+        };
+        if stmts.is_empty() {
+            return Err(input.error(
+                "expected action body, e.g. `Action! { something().await; RunStatus::Success }`",
+            ));
+        }
         Ok(Self {
-            closure,
+            attrs,
             action_name,
+            body: ActionBody::Block { captures, stmts },
         })
     }
 }
 
-impl ActionData {
-    pub fn parse_token(&self) -> TokenStream2 {
-        if matches!(self.closure.body.as_ref(), &Expr::Async(_)) {
-            // Async body
-            // no need capture anything
-            let body = self.closure.body.as_ref();
-            if let Some(action_name) = &self.action_name {
-                return quote! {
-                ::bhv_async::composite::Composite::new
-                    (
-                        #action_name, || Box::pin(#body)
-                    )
-                };
-            } else {
-                return quote! {
-                ::bhv_async::composite::Composite::new_action
-                    (
-                        || Box::pin(#body)
-                    )
-                };
-            };
-        }
+/// `clone a, b =>`, empty if body does not start with it
+fn parse_captures(input: syn::parse::ParseStream) -> syn::Result<Vec<Ident>> {
+    let fork = input.fork();
+    let is_clone = fork.parse::<Ident>().is_ok_and(|ident| ident == "clone") && fork.peek(Ident);
+    if !is_clone {
+        return Ok(vec![]);
+    }
+    input.parse::<Ident>()?;
+    let mut captures = vec![input.parse::<Ident>()?];
+    while input.peek(Token![,]) {
+        input.parse::<Token![,]>()?;
+        captures.push(input.parse::<Ident>()?);
+    }
+    input.parse::<Token![=>]>().map_err(|err| {
+        syn::Error::new(
+            err.span(),
+            "expected `=>` after captures: `clone a, b => { ... }`",
+        )
+    })?;
+    Ok(captures)
+}
 
-        // more thing than async
-        let capture = if self.closure.capture.is_some() {
-            quote!(move)
-        } else {
-            quote!()
-        };
+/// Body must be an async block, or a block returning async blocks
+fn check_body(body: &Expr) -> syn::Result<()> {
+    match body {
+        Expr::Async(_) | Expr::Block(_) => Ok(()),
+        _ => Err(syn::Error::new(
+            body.span(),
+            "expected `async { ... }` or a block ending with `async { ... }`, e.g. `|| async { RunStatus::Success }`",
+        )),
+    }
+}
 
-        let Expr::Block(expr_block) = self.closure.body.as_ref() else {
-            panic!("Expect body Block")
-        };
-        let stmts = &*expr_block.block.stmts;
-        let mapping_statements = stmts.iter().map(|stmt| match stmt {
-            Stmt::Expr(Expr::Async(expr_async), _) => {
-                quote! {
-                    Box::pin(#expr_async)
+/// Box every task returned by setup block of closure:
+/// `return` anywhere (except in nested closures, async blocks and items)
+/// and tail expression, through `if`, `match` and blocks.
+/// Tasks can then have different types.
+fn box_returns(closure: &mut ExprClosure) -> syn::Result<()> {
+    let Expr::Block(expr_block) = closure.body.as_mut() else {
+        return Ok(());
+    };
+    let mut visitor = BoxReturns { error: None };
+    visitor.visit_block_mut(&mut expr_block.block);
+    if let Some(error) = visitor.error {
+        return Err(error);
+    }
+    box_tail(&mut expr_block.block);
+    Ok(())
+}
+
+struct BoxReturns {
+    error: Option<syn::Error>,
+}
+
+impl VisitMut for BoxReturns {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        match expr {
+            // returns of their own
+            Expr::Closure(_) | Expr::Async(_) => {}
+            Expr::Return(expr_return) => match expr_return.expr.as_mut() {
+                Some(task) => {
+                    self.visit_expr_mut(task);
+                    **task = parse_quote!(::std::boxed::Box::pin(#task));
                 }
+                None => {
+                    self.error.get_or_insert(syn::Error::new(
+                        expr_return.span(),
+                        "action must return a task: `return async { ... }`",
+                    ));
+                }
+            },
+            _ => syn::visit_mut::visit_expr_mut(self, expr),
+        }
+    }
+
+    fn visit_item_mut(&mut self, _item: &mut syn::Item) {}
+}
+
+fn box_tail(block: &mut Block) {
+    if let Some(Stmt::Expr(tail, None)) = block.stmts.last_mut() {
+        box_tail_expr(tail);
+    }
+}
+
+fn box_tail_expr(expr: &mut Expr) {
+    match expr {
+        Expr::If(expr_if) => {
+            box_tail(&mut expr_if.then_branch);
+            if let Some((_, else_branch)) = &mut expr_if.else_branch {
+                box_tail_expr(else_branch);
             }
-            Stmt::Expr(Expr::Return(expr_return), _) => {
-                // with_return
-                let inner_return = expr_return
-                    .expr
-                    .as_ref()
-                    .expect("Should be Some Expr::Async")
-                    .as_ref();
-                quote! {
-                    return Box::pin(#inner_return);
+        }
+        Expr::Match(expr_match) => {
+            for arm in &mut expr_match.arms {
+                box_tail_expr(&mut arm.body);
+            }
+        }
+        Expr::Block(expr_block) => box_tail(&mut expr_block.block),
+        // boxed already
+        Expr::Return(_) => {}
+        _ => *expr = parse_quote!(::std::boxed::Box::pin(#expr)),
+    }
+}
+
+/// Block-like tail without value: `for`, `while`, `if` without `else`
+fn is_unit(tail: &Expr) -> bool {
+    match tail {
+        Expr::ForLoop(_) | Expr::While(_) => true,
+        Expr::If(expr_if) => expr_if.else_branch.is_none(),
+        _ => false,
+    }
+}
+
+impl ActionData {
+    pub fn parse_token(&self) -> TokenStream2 {
+        let action = match &self.body {
+            ActionBody::Closure(closure) => {
+                let capture = &closure.capture;
+                let inputs = &closure.inputs;
+                match closure.body.as_ref() {
+                    // no setup, just create the task
+                    body @ Expr::Async(_) => quote! {
+                        #capture |#inputs| ::std::boxed::Box::pin(#body)
+                    },
+                    // checked by parse, returns are boxed
+                    body => quote! {
+                        #capture |#inputs| -> ::bhv_async::composite::BoxAction #body
+                    },
                 }
             }
-            _ => {
+            ActionBody::Block { captures, stmts } => {
+                // body without value is a success
+                let success = match stmts.last() {
+                    Some(Stmt::Expr(tail, None)) if !is_unit(tail) => quote!(),
+                    Some(Stmt::Expr(Expr::Return(_), _)) => quote!(),
+                    _ => quote!(::bhv_async::RunStatus::Success),
+                };
+                let task = quote! {
+                    #(let #captures = ::core::clone::Clone::clone(&#captures);)*
+                    ::std::boxed::Box::pin(async move {
+                        #(#stmts)*
+                        #success
+                    }) as ::bhv_async::composite::BoxAction
+                };
+                // variables stay usable after the macro
                 quote! {
-                    #stmt
+                    {
+                        #(let #captures = ::core::clone::Clone::clone(&#captures);)*
+                        move || { #task }
+                    }
                 }
             }
-        });
-
-        if let Some(action_name) = &self.action_name {
-            quote! {
-                ::bhv_async::composite::Composite::new
-                    (
-                        #action_name, #capture || { #(#mapping_statements)* }
-                    )
-            }
-        } else {
-            quote! {
-                ::bhv_async::composite::Composite::new_action
-                    (
-                        #capture || { #(#mapping_statements)* }
-                    )
-            }
+        };
+        let composite = match &self.action_name {
+            Some(action_name) => quote! {
+                ::bhv_async::composite::Composite::new(#action_name, #action)
+            },
+            None => quote! {
+                ::bhv_async::composite::Composite::new_action(#action)
+            },
+        };
+        match self.attrs.is_empty() {
+            true => composite,
+            false => self.attrs.apply(composite),
         }
     }
 }
@@ -1,41 +1,87 @@
-use proc_macro2::{Ident, TokenStream};
+use proc_macro2::TokenStream;
 use quote::quote;
-use syn::{parse::Parse, ExprClosure, Token};
+use syn::{parse::Parse, Expr, ExprClosure, Token};
+
+use crate::{attributes, groups::into_composite};
 
 pub struct DecoratorData {
     condition: ExprClosure,
     task_creation: Option<ExprClosure>,
-    or_another_composite: Option<Ident>,
+    or_another_composite: Option<Expr>,
 }
 
-impl Parse for DecoratorData {
-    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
-        let condition = input.parse::<ExprClosure>()?;
-        // eprintln!("can work ?? {condition:#?}");
+/// Closure start by `|`, `||`, `move` or `async`
+fn peek_closure(input: syn::parse::ParseStream) -> bool {
+    input.peek(Token![|])
+        || input.peek(Token![||])
+        || input.peek(Token![move])
+        || input.peek(Token![async])
+}
+
+/// Child of a decorator, a closure is the task of an Action
+fn parse_decorated_child(
+    input: syn::parse::ParseStream,
+) -> syn::Result<(Option<ExprClosure>, Option<Expr>)> {
+    let child = input.parse::<Expr>().map_err(|err| {
+        syn::Error::new(
+            err.span(),
+            "expected child: a task closure `|| async { ... }` or an expression converting into Composite",
+        )
+    })?;
+    Ok(match child {
+        Expr::Closure(closure) => (Some(closure), None),
+        child => (None, Some(child)),
+    })
+}
+
+/// Optional trailing comma, then nothing
+fn finish(input: syn::parse::ParseStream, message: &str) -> syn::Result<()> {
+    if input.peek(Token![,]) {
         input.parse::<Token![,]>()?;
+    }
+    if !input.is_empty() {
+        return Err(input.error(message));
+    }
+    Ok(())
+}
 
-        if let Ok(task_creation) = input.parse::<ExprClosure>() {
-            return Ok(Self {
-                condition,
-                task_creation: Some(task_creation),
-                or_another_composite: None,
-            });
-        }
+fn child_tokens(
+    task_creation: &Option<ExprClosure>,
+    or_another_composite: &Option<Expr>,
+) -> TokenStream {
+    match task_creation {
+        Some(task_creation) => quote!(::bhv_async::macros::Action!(#task_creation)),
+        None => into_composite(or_another_composite.as_ref().unwrap()),
+    }
+}
 
-        if let Ok(or_another_composite) = input.parse::<Ident>() {
-            return Ok(Self {
-                condition,
-                task_creation: None,
-                or_another_composite: Some(or_another_composite),
-            });
+impl Parse for DecoratorData {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        if !peek_closure(input) {
+            return Err(input.error("expected condition closure, e.g. `|| is_ready()`"));
         }
-        panic!("Decorator have no TaskCreation or another Composite")
+        let condition = input.parse::<ExprClosure>()?;
+        input.parse::<Token![,]>().map_err(|err| {
+            syn::Error::new(err.span(), "expected `,` between condition and child")
+        })?;
+
+        let (task_creation, or_another_composite) = parse_decorated_child(input)?;
+        finish(
+            input,
+            "unexpected token, decorator take a condition and one child",
+        )?;
+        Ok(Self {
+            condition,
+            task_creation,
+            or_another_composite,
+        })
     }
 }
 
 pub enum DecoratorType {
     Decorator,
     DecoratorContinue,
+    InterruptAction,
 }
 
 impl DecoratorData {
@@ -45,23 +91,127 @@ impl DecoratorData {
             DecoratorType::DecoratorContinue => {
                 quote!(::bhv_async::common_behaviors::DecoratorContinue::new)
             }
+            DecoratorType::InterruptAction => {
+                quote!(::bhv_async::common_behaviors::InterruptAction::new)
+            }
         };
         let condition = &self.condition;
-        if let Some(task_creation) = self.task_creation.as_ref().map(|i| quote!(#i)) {
-            quote! {
-                #new_struct_path
-                    (
-                        #condition, Action!(#task_creation)
-                    )
+        let child = child_tokens(&self.task_creation, &self.or_another_composite);
+        quote! {
+            #new_struct_path
+                (
+                    #condition, #child
+                )
+        }
+    }
+}
+
+/// Node wrapping one child without condition
+pub struct SingleChildData {
+    task_creation: Option<ExprClosure>,
+    or_another_composite: Option<Expr>,
+}
+
+impl Parse for SingleChildData {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let (task_creation, or_another_composite) = parse_decorated_child(input)?;
+        finish(input, "unexpected token, node take only one child")?;
+        Ok(Self {
+            task_creation,
+            or_another_composite,
+        })
+    }
+}
+
+pub enum SingleChildType {
+    Inverter,
+    UntilSuccess,
+    UntilFailure,
+}
+
+impl SingleChildData {
+    pub fn parse_token(&self, parse_for: SingleChildType) -> TokenStream {
+        let new_struct_path = match parse_for {
+            SingleChildType::Inverter => quote!(::bhv_async::common_behaviors::Inverter::new),
+            SingleChildType::UntilSuccess => {
+                quote!(::bhv_async::common_behaviors::UntilSuccess::new)
             }
-        } else {
-            let another_composite = self.or_another_composite.as_ref().unwrap();
-            quote! {
-                #new_struct_path
-                    (
-                        #condition, #another_composite
-                    )
+            SingleChildType::UntilFailure => {
+                quote!(::bhv_async::common_behaviors::UntilFailure::new)
             }
+        };
+        let child = child_tokens(&self.task_creation, &self.or_another_composite);
+        quote! {
+            #new_struct_path(#child)
+        }
+    }
+}
+
+/// Duration of Timeout and Wait: `"2s"`, `"500ms"`, seconds or a Duration
+fn parse_duration(input: syn::parse::ParseStream) -> syn::Result<TokenStream> {
+    let duration = input.parse::<Expr>().map_err(|err| {
+        syn::Error::new(
+            err.span(),
+            "expected duration: \"2s\", \"500ms\", seconds or a Duration",
+        )
+    })?;
+    attributes::duration(&duration)
+}
+
+/// `Timeout! { "2s", child }`
+pub struct TimeoutData {
+    duration: TokenStream,
+    task_creation: Option<ExprClosure>,
+    or_another_composite: Option<Expr>,
+}
+
+impl Parse for TimeoutData {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let duration = parse_duration(input)?;
+        input.parse::<Token![,]>().map_err(|err| {
+            syn::Error::new(err.span(), "expected `,` between duration and child")
+        })?;
+        let (task_creation, or_another_composite) = parse_decorated_child(input)?;
+        finish(
+            input,
+            "unexpected token, timeout take a duration and one child",
+        )?;
+        Ok(Self {
+            duration,
+            task_creation,
+            or_another_composite,
+        })
+    }
+}
+
+impl TimeoutData {
+    pub fn parse_token(&self) -> TokenStream {
+        let duration = &self.duration;
+        let child = child_tokens(&self.task_creation, &self.or_another_composite);
+        quote! {
+            ::bhv_async::common_behaviors::Timeout::new(#duration, #child)
+        }
+    }
+}
+
+/// `Wait! { "500ms" }`
+pub struct WaitData {
+    duration: TokenStream,
+}
+
+impl Parse for WaitData {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let duration = parse_duration(input)?;
+        finish(input, "unexpected token, wait take only a duration")?;
+        Ok(Self { duration })
+    }
+}
+
+impl WaitData {
+    pub fn parse_token(&self) -> TokenStream {
+        let duration = &self.duration;
+        quote! {
+            ::bhv_async::common_behaviors::Wait::new(#duration)
         }
     }
 }
@@ -0,0 +1,177 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_quote, Data, DeriveInput, Fields, LitStr, Member};
+
+/// How a field is handled by `#[derive(Behavior)]`
+#[derive(PartialEq)]
+enum FieldKind {
+    /// Cloned, like settings of node. Kept between runs of a reused task,
+    /// so anything a run change must be State
+    Config,
+    /// `#[bhv(child)]`: a Composite
+    Child,
+    /// `#[bhv(children)]`: Vec, Rc<[_]>,... of Composite
+    Children,
+    /// `#[bhv(state)]`: state of a run, Default on clone and after finish
+    State,
+}
+
+struct Field {
+    member: Member,
+    kind: FieldKind,
+}
+
+pub struct BehaviorDerive {
+    input: DeriveInput,
+    name: Option<LitStr>,
+    fields: Vec<Field>,
+}
+
+impl BehaviorDerive {
+    pub fn new(input: DeriveInput) -> syn::Result<Self> {
+        let mut name = None;
+        for attr in input
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("bhv"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("name") {
+                    name = Some(meta.value()?.parse()?);
+                    return Ok(());
+                }
+                Err(meta.error("unknown attribute, expected `#[bhv(name = \"...\")]`"))
+            })?;
+        }
+
+        let Data::Struct(data) = &input.data else {
+            return Err(syn::Error::new(
+                input.ident.span(),
+                "Behavior can only be derived for struct",
+            ));
+        };
+        if let Fields::Unit = data.fields {
+            return Err(syn::Error::new(
+                input.ident.span(),
+                "Behavior need fields, a node without state is an Action",
+            ));
+        }
+
+        let mut fields = vec![];
+        for (index, field) in data.fields.iter().enumerate() {
+            let member = match &field.ident {
+                Some(ident) => Member::Named(ident.clone()),
+                None => Member::Unnamed(index.into()),
+            };
+            let mut kind = FieldKind::Config;
+            for attr in field
+                .attrs
+                .iter()
+                .filter(|attr| attr.path().is_ident("bhv"))
+            {
+                attr.parse_nested_meta(|meta| {
+                    if kind != FieldKind::Config {
+                        return Err(meta.error("field can have only one of child, children, state"));
+                    }
+                    kind = if meta.path.is_ident("child") {
+                        FieldKind::Child
+                    } else if meta.path.is_ident("children") {
+                        FieldKind::Children
+                    } else if meta.path.is_ident("state") {
+                        FieldKind::State
+                    } else {
+                        return Err(meta.error(
+                            "unknown attribute, expected `#[bhv(child)]`, `#[bhv(children)]` or `#[bhv(state)]`",
+                        ));
+                    };
+                    Ok(())
+                })?;
+            }
+            fields.push(Field { member, kind });
+        }
+        Ok(Self {
+            input,
+            name,
+            fields,
+        })
+    }
+
+    pub fn parse_token(&self) -> TokenStream2 {
+        let ident = &self.input.ident;
+        let name = match &self.name {
+            Some(name) => quote!(#name),
+            None => quote!(::core::stringify!(#ident)),
+        };
+        let (impl_generics, ty_generics, where_clause) = self.input.generics.split_for_impl();
+
+        let clone_fields = self.fields.iter().map(|Field { member, kind }| match kind {
+            FieldKind::State => quote!(#member: ::core::default::Default::default()),
+            _ => quote!(#member: ::core::clone::Clone::clone(&self.#member)),
+        });
+        let reset_fields = self
+            .fields
+            .iter()
+            .filter(|field| field.kind == FieldKind::State)
+            .map(
+                |Field { member, .. }| quote!(self.#member = ::core::default::Default::default();),
+            );
+        let children = self.fields.iter().map(|Field { member, kind }| match kind {
+            FieldKind::Child => quote!(children.push(&self.#member);),
+            FieldKind::Children => quote!(children.extend(self.#member.iter());),
+            _ => quote!(),
+        });
+
+        let mut from_generics = self.input.generics.clone();
+        from_generics
+            .make_where_clause()
+            .predicates
+            .push(parse_quote! {
+                #ident #ty_generics: ::core::future::Future<Output = ::bhv_async::RunStatus> + 'static
+            });
+        let (_, _, from_where_clause) = from_generics.split_for_impl();
+
+        quote! {
+            impl #impl_generics ::core::clone::Clone for #ident #ty_generics #where_clause {
+                fn clone(&self) -> Self {
+                    Self {
+                        #(#clone_fields,)*
+                    }
+                }
+            }
+
+            impl #impl_generics ::bhv_async::composite::Reset for #ident #ty_generics #where_clause {
+                fn reset(&mut self) {
+                    #(#reset_fields)*
+                }
+            }
+
+            impl #impl_generics ::bhv_async::composite::Behavior for #ident #ty_generics #where_clause {
+                const NAME: &'static str = #name;
+
+                fn children(&self) -> ::std::vec::Vec<&::bhv_async::composite::Composite> {
+                    #[allow(unused_mut)]
+                    let mut children = ::std::vec::Vec::new();
+                    #(#children)*
+                    children
+                }
+            }
+
+            impl #impl_generics ::core::convert::From<#ident #ty_generics>
+                for ::bhv_async::composite::Composite #from_where_clause
+            {
+                #[track_caller]
+                fn from(value: #ident #ty_generics) -> Self {
+                    ::bhv_async::composite::Composite::new(#name, move || {
+                        let value_go = ::core::clone::Clone::clone(&value);
+                        // derived reset only overwrite state fields in place
+                        ::std::boxed::Box::pin(unsafe {
+                            ::bhv_async::composite::PinnedReusable::new(value_go)
+                        })
+                    })
+                    .with_kind(::core::stringify!(#ident))
+                    .with_reusable(true)
+                }
+            }
+        }
+    }
+}
@@ -1,77 +1,303 @@
-use proc_macro2::{Group, Ident, Punct, TokenStream as TokenStream2};
-use quote::quote;
-use syn::{parse::Parse, Token};
+use proc_macro2::{Span, TokenStream as TokenStream2};
+
+use crate::attributes::{GroupRng, NodeAttrs};
+use quote::{quote, quote_spanned};
+use syn::{parse::Parse, spanned::Spanned, Expr, Ident, Token};
 
 pub struct GroupBehaviorData {
-    actions: Vec<TokenStream2>,
+    attrs: NodeAttrs,
+    actions: Vec<GroupChild>,
 }
 
-impl Parse for GroupBehaviorData {
-    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
-        // eprintln!("{input:#?}");
-        let mut actions = vec![];
-
-        loop {
-            let Ok(ident) = input.parse::<Ident>() else {
-                break;
-            };
-            if input.parse::<Token![,]>().is_ok() {
-                actions.push(quote!(#ident));
-                continue;
-            }
+/// Child of group: `#[attrs] if guard => child` or `..spread`
+pub struct GroupChild {
+    attrs: NodeAttrs,
+    /// Child is added only if true, evaluated when tree is built
+    guard: Option<Expr>,
+    /// Child of WeightedSelector: `weight => child`
+    weight: Option<Expr>,
+    child: Expr,
+    /// Child is an iterator of children: `..waypoints.iter().map(patrol_to)`
+    spread: bool,
+}
 
-            // this is not ending by ","
-            // maybe this is macro
-            // so it will follow syntax
-            // Ident Punct Group
-            let punct = input.parse::<Punct>()?;
-            if punct.as_char() != '!' {
-                panic!("Parse error, expect Punct macro => '!'")
+/// Parse one child of group
+/// it can be any expression converting into Composite:
+/// variable, macro (`Action! { ... }`), call (`Inverter::new(x)`),...
+fn parse_child(input: syn::parse::ParseStream) -> syn::Result<Expr> {
+    input.parse::<Expr>().map_err(|err| {
+        syn::Error::new(
+            err.span(),
+            "expected child: an expression converting into Composite, like `patrol` or `Action! { || async { ... } }`",
+        )
+    })
+}
+
+/// Children separated by ',' (trailing ',' allowed)
+fn parse_childs<T>(
+    input: syn::parse::ParseStream,
+    mut parse_one: impl FnMut(syn::parse::ParseStream) -> syn::Result<T>,
+) -> syn::Result<Vec<T>> {
+    let mut childs = vec![];
+    while !input.is_empty() {
+        childs.push(parse_one(input)?);
+        if input.is_empty() {
+            break;
+        }
+        input
+            .parse::<Token![,]>()
+            .map_err(|err| syn::Error::new(err.span(), "expected `,` between children"))?;
+    }
+
+    if childs.is_empty() {
+        return Err(syn::Error::new(
+            Span::call_site(),
+            "group need at least one child, e.g. `Sequence! { Action! { || async { RunStatus::Success } } }`",
+        ));
+    }
+    Ok(childs)
+}
+
+impl GroupChild {
+    fn parse(input: syn::parse::ParseStream, weighted: bool) -> syn::Result<Self> {
+        let mut attrs = NodeAttrs::parse_outer(input)?;
+        let guard = if input.peek(Token![if]) {
+            input.parse::<Token![if]>()?;
+            let guard = Expr::parse_without_eager_brace(input)?;
+            input.parse::<Token![=>]>().map_err(|err| {
+                syn::Error::new(err.span(), "expected `=>` after guard: `if cond => child`")
+            })?;
+            attrs.parse_more(input)?;
+            Some(guard)
+        } else {
+            None
+        };
+        attrs.reject_rng()?;
+        let weight = if weighted {
+            let weight = input.parse::<Expr>()?;
+            input.parse::<Token![=>]>().map_err(|err| {
+                syn::Error::new(err.span(), "expected `=>` after weight: `weight => child`")
+            })?;
+            Some(weight)
+        } else {
+            None
+        };
+        let spread = !weighted && input.peek(Token![..]);
+        if spread {
+            input.parse::<Token![..]>()?;
+        }
+        Ok(Self {
+            attrs,
+            guard,
+            weight,
+            child: parse_child(input)?,
+            spread,
+        })
+    }
+
+    /// Known when macro expand, children can go in an array
+    fn is_static(&self) -> bool {
+        !self.attrs.has_cfg() && self.guard.is_none() && !self.spread
+    }
+
+    /// Child converted into Composite, with its attributes
+    fn composite(&self, child: TokenStream2) -> TokenStream2 {
+        let child = match self.attrs.is_empty() {
+            true => child,
+            false => self.attrs.apply(child),
+        };
+        match &self.weight {
+            Some(weight) => quote!(((#weight) as f32, #child)),
+            None => child,
+        }
+    }
+
+    /// Statement adding child to `childs` vec
+    fn push(&self, childs: &Ident) -> TokenStream2 {
+        let push = match self.spread {
+            true => {
+                let iter = &self.child;
+                // not a variable of user, no binding
+                let item = Ident::new("child", Span::mixed_site());
+                let child = self.composite(quote_spanned! {iter.span()=>
+                    ::core::convert::Into::<::bhv_async::composite::Composite>::into(#item)
+                });
+                // closure of macro, not of user
+                let map = quote_spanned!(Span::mixed_site()=> |#item| #child);
+                quote_spanned! {iter.span()=>
+                    #childs.extend(::core::iter::IntoIterator::into_iter(#iter).map(#map));
+                }
             }
-            let group = input.parse::<Group>()?;
-            // eprintln!("This?? {ident:#?}\n{punct:#?}\n{group:#?}");
-            actions.push(quote! {
-                #ident #punct #group
-            });
-            if input.parse::<Token![,]>().is_ok() {
-                continue;
-            } else {
-                break;
+            false => {
+                let child = self.composite(into_composite(&self.child));
+                quote!(#childs.push(#child);)
             }
+        };
+        let push = match &self.guard {
+            Some(guard) => quote!(if #guard { #push }),
+            None => push,
+        };
+        let cfg = self.attrs.cfg();
+        quote! {
+            #cfg
+            #push
         }
+    }
+}
 
-        if actions.is_empty() {
-            panic!("Childs components empty...")
+/// Children as an array, or a vec built by statements
+/// when a child has cfg, guard or is spread
+fn childs_tokens(childs: &[GroupChild]) -> TokenStream2 {
+    if childs.iter().all(GroupChild::is_static) {
+        let childs = childs
+            .iter()
+            .map(|child| child.composite(into_composite(&child.child)));
+        return quote!([#(#childs,)*]);
+    }
+    // hygienic, guards and spreads still see variables of user named `childs`
+    let vec = Ident::new("childs", Span::mixed_site());
+    let pushes = childs.iter().map(|child| child.push(&vec));
+    quote! {
+        {
+            #[allow(unused_mut)]
+            let mut #vec = ::std::vec::Vec::new();
+            #(#pushes)*
+            #vec
         }
-        // eprintln!("result:\n{actions:#?}");
+    }
+}
 
-        Ok(Self { actions })
+/// Constructor of random group: `with_seed`, `with_rng` or `new`
+fn random_group_tokens(
+    attrs: &NodeAttrs,
+    path: TokenStream2,
+    childs: TokenStream2,
+) -> TokenStream2 {
+    match attrs.rng() {
+        Some(GroupRng::Seed(seed)) => quote!(#path::with_seed(#childs, #seed)),
+        Some(GroupRng::Rng(rng)) => quote!(#path::with_rng(#childs, #rng)),
+        None => quote!(#path::new(#childs)),
+    }
+}
+
+/// With attributes (`#![timeout = "2s"]`), group become a Composite
+fn group_tokens(attrs: &NodeAttrs, group: TokenStream2) -> TokenStream2 {
+    match attrs.is_empty() {
+        true => group,
+        false => attrs.apply(group),
+    }
+}
+
+impl Parse for GroupBehaviorData {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let attrs = NodeAttrs::parse_inner(input)?;
+        attrs.reject_cfg()?;
+        let actions = parse_childs(input, |input| GroupChild::parse(input, false))?;
+        Ok(Self { attrs, actions })
+    }
+}
+
+/// Child expression converted into Composite.
+/// A variable child (`patrol`, `patrol.clone()`) give its name as binding.
+pub fn into_composite(child: &Expr) -> TokenStream2 {
+    // located at child, not at macro
+    let composite = quote_spanned! {child.span()=>
+        ::core::convert::Into::<::bhv_async::composite::Composite>::into(#child)
+    };
+    match binding(child) {
+        Some(binding) => {
+            let binding = binding.to_string();
+            quote!(#composite.with_binding(#binding))
+        }
+        None => composite,
+    }
+}
+
+fn binding(child: &Expr) -> Option<&syn::Ident> {
+    match child {
+        Expr::Path(path) if path.qself.is_none() => path.path.get_ident(),
+        Expr::MethodCall(call) if call.method == "clone" && call.args.is_empty() => {
+            binding(&call.receiver)
+        }
+        Expr::Paren(paren) => binding(&paren.expr),
+        _ => None,
     }
 }
 
 pub enum GroupBehaviorType {
     Sequence,
     PrioritySelector,
+    RandomSequence,
+    RandomSelector,
 }
 
 impl GroupBehaviorData {
-    pub fn parse_token(&self, parse_for: GroupBehaviorType) -> TokenStream2 {
-        let actions = self.actions.iter().map(|s| {
-            quote! {
-                #s.into()
+    pub fn parse_token(&self, parse_for: GroupBehaviorType) -> syn::Result<TokenStream2> {
+        let actions = childs_tokens(&self.actions);
+        let group = match parse_for {
+            GroupBehaviorType::Sequence => {
+                self.attrs.reject_rng()?;
+                quote! {::bhv_async::common_behaviors::Sequence::new(#actions)}
             }
-        });
-        let new_struct_path = match parse_for {
-            GroupBehaviorType::Sequence => quote! {::bhv_async::common_behaviors::Sequence::new},
             GroupBehaviorType::PrioritySelector => {
-                quote! {::bhv_async::common_behaviors::PrioritySelector::new}
+                self.attrs.reject_rng()?;
+                quote! {::bhv_async::common_behaviors::PrioritySelector::new(#actions)}
             }
+            GroupBehaviorType::RandomSequence => random_group_tokens(
+                &self.attrs,
+                quote!(::bhv_async::common_behaviors::RandomSequence),
+                actions,
+            ),
+            GroupBehaviorType::RandomSelector => random_group_tokens(
+                &self.attrs,
+                quote!(::bhv_async::common_behaviors::RandomSelector),
+                actions,
+            ),
         };
-        quote! {
-        #new_struct_path
-        (
-            [#(#actions,)*]
-        )
-        }
+        Ok(group_tokens(&self.attrs, group))
+    }
+}
+
+/// Group with weight for each child
+/// weight => child,
+pub struct WeightedGroupData {
+    attrs: NodeAttrs,
+    actions: Vec<GroupChild>,
+}
+
+impl Parse for WeightedGroupData {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let attrs = NodeAttrs::parse_inner(input)?;
+        attrs.reject_cfg()?;
+        let actions = parse_childs(input, |input| GroupChild::parse(input, true))?;
+        Ok(Self { attrs, actions })
+    }
+}
+
+impl WeightedGroupData {
+    pub fn parse_token(&self) -> TokenStream2 {
+        let actions = childs_tokens(&self.actions);
+        let group = random_group_tokens(
+            &self.attrs,
+            quote!(::bhv_async::common_behaviors::WeightedSelector),
+            actions,
+        );
+        group_tokens(&self.attrs, group)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spread_child_has_no_binding() {
+        let group: GroupBehaviorData = syn::parse_quote!(patrol, ..waypoints);
+        let tokens = group
+            .parse_token(GroupBehaviorType::Sequence)
+            .unwrap()
+            .to_string();
+        assert_eq!(tokens.matches("with_binding").count(), 1);
+        assert!(tokens.contains("with_binding (\"patrol\")"));
     }
 }
@@ -1,16 +1,23 @@
 #![feature(proc_macro_expand)]
 #![allow(non_snake_case)]
+mod attributes;
+mod behavior;
 mod composite;
 mod decorator;
+mod derive;
 mod groups;
+mod tree;
 
 use groups::*;
 use proc_macro::TokenStream;
 use syn::parse_macro_input;
 
+use behavior::{BehaviorArgs, BehaviorFn};
 use composite::ActionData;
+use derive::BehaviorDerive;
+use tree::TreeData;
 
-use self::decorator::DecoratorData;
+use self::decorator::{DecoratorData, SingleChildData, SingleChildType, TimeoutData, WaitData};
 
 /// Example
 /// let action = Action! {
@@ -19,7 +26,7 @@ use self::decorator::DecoratorData;
 ///         RunStatus::Success
 ///     }
 /// };
-
+///
 /// let action_with_capture = Action! {
 ///     move || {
 ///         let action_move = action.clone();
@@ -29,6 +36,14 @@ use self::decorator::DecoratorData;
 ///         }
 ///     }
 /// };
+///
+/// Attributes before the action, shorthands wrap it in decorators (first is innermost):
+/// let attack = Action! {
+///     #[name = "attack", tags("combat"), description = "hit target"]
+///     #[timeout = "2s", retry = 3] // also: repeat = n, invert, condition = || .., interrupt = || ..
+///     // retry = 3: run again up to 3 times after failure, repeat = 3: run 3 times at most
+///     || async { hit().await }
+/// };
 #[proc_macro]
 pub fn Action(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as ActionData);
@@ -73,10 +88,28 @@ pub fn Action(input: TokenStream) -> TokenStream {
 ///             },
 ///         }
 /// };
+///
+/// Children take same attributes as `Action!`, `#![...]` apply to group itself
+/// (group is then given as a Composite):
+/// let combat = Sequence! {
+///     #![name = "combat", timeout = "10s"]
+///     #[retry = 3] approach,
+///     attack,
+/// };
+///
+/// Children can be left out or generated when tree is built:
+/// let patrol = Sequence! {
+///     #[cfg(feature = "debug")] log_state,
+///     if alert => call_help,
+///     ..waypoints.iter().map(|point| move_to(*point)),
+/// };
 #[proc_macro]
 pub fn Sequence(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as GroupBehaviorData);
-    input.parse_token(GroupBehaviorType::Sequence).into()
+    input
+        .parse_token(GroupBehaviorType::Sequence)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
 }
 
 #[proc_macro]
@@ -84,9 +117,47 @@ pub fn PrioritySelector(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as GroupBehaviorData);
     input
         .parse_token(GroupBehaviorType::PrioritySelector)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Order is random, `#![seed = 42]` or `#![rng = fastrand::Rng::with_seed(42)]`
+/// make it deterministic:
+/// let wander = RandomSequence! { #![seed = 7] look_around, sniff, walk };
+#[proc_macro]
+pub fn RandomSequence(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as GroupBehaviorData);
+    input
+        .parse_token(GroupBehaviorType::RandomSequence)
+        .unwrap_or_else(syn::Error::into_compile_error)
         .into()
 }
 
+#[proc_macro]
+pub fn RandomSelector(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as GroupBehaviorData);
+    input
+        .parse_token(GroupBehaviorType::RandomSelector)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// let picked = WeightedSelector! {
+///     1 => patrol,
+///     3.5 => Action! {
+///         || async {
+///             sleep(Duration::from_secs(1)).await;
+///             RunStatus::Success
+///         }
+///     },
+/// };
+/// Generator is given like RandomSequence: `#![seed = 42]` or `#![rng = ...]`
+#[proc_macro]
+pub fn WeightedSelector(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as WeightedGroupData);
+    input.parse_token().into()
+}
+
 #[proc_macro]
 pub fn Decorator(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DecoratorData);
@@ -102,3 +173,135 @@ pub fn DecoratorContinue(input: TokenStream) -> TokenStream {
         .parse_token(decorator::DecoratorType::DecoratorContinue)
         .into()
 }
+
+/// Abort the running child when condition become false
+/// let guard = InterruptAction! { || target_visible(), chase };
+#[proc_macro]
+pub fn InterruptAction(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DecoratorData);
+    input
+        .parse_token(decorator::DecoratorType::InterruptAction)
+        .into()
+}
+
+/// let not_found = Inverter! { search };
+/// let not_found = Inverter! { || async { RunStatus::Failure } };
+#[proc_macro]
+pub fn Inverter(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as SingleChildData);
+    input.parse_token(SingleChildType::Inverter).into()
+}
+
+/// let opened = UntilSuccess! { try_open_door };
+#[proc_macro]
+pub fn UntilSuccess(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as SingleChildData);
+    input.parse_token(SingleChildType::UntilSuccess).into()
+}
+
+/// let drained = UntilFailure! { pop_item };
+#[proc_macro]
+pub fn UntilFailure(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as SingleChildData);
+    input.parse_token(SingleChildType::UntilFailure).into()
+}
+
+/// Fail the child if it run longer than duration,
+/// duration is `"2s"`, `"500ms"`, seconds or a Duration:
+/// let bounded = Timeout! { "2s", search };
+/// let bounded = Timeout! { Duration::from_millis(300), || async { RunStatus::Success } };
+#[proc_macro]
+pub fn Timeout(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as TimeoutData);
+    input.parse_token().into()
+}
+
+/// let pause = Wait! { "500ms" };
+#[proc_macro]
+pub fn Wait(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as WaitData);
+    input.parse_token().into()
+}
+
+/// Whole tree in XML-like syntax, return a Composite.
+/// Child is an element or `{ composite }` of an existing tree.
+/// Attribute value is a literal or `{ expression }`, duration literal is in seconds.
+///
+/// let guard = tree! {
+///     <PrioritySelector>
+///         <Decorator condition={|| enemy_visible()}>
+///             <Action name="attack" do={attack} timeout=5/>
+///         </Decorator>
+///         <UntilSuccess retry=2>
+///             <Action do={async { open_door().await }}/>
+///         </UntilSuccess>
+///         <Wait duration=0.5/>
+///         {patrol}
+///     </PrioritySelector>
+/// };
+///
+/// Nodes: Action (do), Sequence, PrioritySelector, RandomSequence (seed),
+/// RandomSelector (seed), WeightedSelector (seed, `weight` on children),
+/// Decorator / DecoratorContinue / InterruptAction (condition), Inverter,
+/// UntilSuccess (retry), UntilFailure (repeat), Timeout (duration), Wait (duration).
+/// `retry` and `repeat` count as the `#[retry]` and `#[repeat]` node attributes:
+/// `retry=2` run again up to 2 times after a failure, `repeat=2` run 2 times at most.
+/// Every node can have `name` and `timeout`.
+#[proc_macro]
+pub fn tree(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as TreeData);
+    input
+        .parse_token()
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Turn an async fn into constructor of a leaf node named after the fn.
+/// Parameters are cloned into each task, so they must be owned and Clone.
+///
+/// #[bhv_async::behavior]
+/// async fn move_to(target: Vec2) -> RunStatus {
+///     walk(target).await
+/// }
+///
+/// let node: Composite = move_to(Vec2::ZERO); // named "move_to"
+///
+/// `#[behavior(name = "walk")]` give another name.
+#[proc_macro_attribute]
+pub fn behavior(args: TokenStream, input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as BehaviorArgs);
+    let input = parse_macro_input!(input as BehaviorFn);
+    input.parse_token(args).into()
+}
+
+/// Implement Clone, Reset, Behavior and conversion into Composite for a custom node,
+/// its Future is written by hand.
+/// - `#[bhv(child)]`: a Composite child
+/// - `#[bhv(children)]`: Vec, Rc<[_]>,... of Composite
+/// - `#[bhv(state)]`: state of a run (running task,...), Default on clone and after finish
+/// - other fields are cloned
+///
+/// Task of node is reused: after a run finish, only state fields are reset
+/// and same task run again. Every field a run change must be `#[bhv(state)]`,
+/// other fields keep their changes in next runs.
+/// Node does not need to be Unpin, it is pinned once and reset in place.
+/// - `#[bhv(name = "...")]` on struct: name of node, type name by default
+///
+/// #[derive(Behavior)]
+/// struct Retry {
+///     times: u32,
+///     #[bhv(child)]
+///     child: Composite,
+///     #[bhv(state)]
+///     task: Option<BoxAction>,
+///     #[bhv(state)]
+///     tries: u32,
+/// }
+#[proc_macro_derive(Behavior, attributes(bhv))]
+pub fn derive_behavior(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as syn::DeriveInput);
+    BehaviorDerive::new(input)
+        .map(|derive| derive.parse_token())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
@@ -0,0 +1,379 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, quote_spanned};
+use syn::{
+    braced, ext::IdentExt, parse::Parse, spanned::Spanned, Expr, ExprLit, Ident, Lit, Token,
+};
+
+use crate::{attributes, groups::into_composite};
+
+/// Value of attribute: `"text"`, `1.5`, `true` or `{ expression }`
+pub enum AttrValue {
+    Lit(Lit),
+    Expr(Expr),
+}
+
+impl Parse for AttrValue {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        if input.peek(syn::token::Brace) {
+            let content;
+            braced!(content in input);
+            return Ok(Self::Expr(content.parse()?));
+        }
+        if input.peek(Lit) {
+            return Ok(Self::Lit(input.parse()?));
+        }
+        Err(input.error("expected attribute value: a literal or `{ expression }`"))
+    }
+}
+
+impl AttrValue {
+    fn span(&self) -> proc_macro2::Span {
+        match self {
+            Self::Lit(lit) => lit.span(),
+            Self::Expr(expr) => expr.span(),
+        }
+    }
+
+    fn to_tokens(&self) -> TokenStream2 {
+        match self {
+            Self::Lit(lit) => quote!(#lit),
+            Self::Expr(expr) => quote!(#expr),
+        }
+    }
+
+    /// Number literal is seconds, string has a unit ("500ms", "2s",...)
+    fn duration(&self) -> syn::Result<TokenStream2> {
+        match self {
+            Self::Lit(lit) => attributes::duration(&Expr::Lit(ExprLit {
+                attrs: vec![],
+                lit: lit.clone(),
+            })),
+            Self::Expr(expr) => Ok(quote!(#expr)),
+        }
+    }
+}
+
+pub struct Attr {
+    name: Ident,
+    value: AttrValue,
+}
+
+/// Child of element
+pub enum Node {
+    /// `<Kind attr=value ...> childs </Kind>` or `<Kind attr=value .../>`
+    Element {
+        kind: Ident,
+        attrs: Vec<Attr>,
+        childs: Vec<Node>,
+    },
+    /// `{ composite }`, an existing tree or anything converting into Composite
+    Expr(Expr),
+}
+
+impl Parse for Node {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        if input.peek(syn::token::Brace) {
+            let content;
+            braced!(content in input);
+            return Ok(Self::Expr(content.parse()?));
+        }
+        if !input.peek(Token![<]) {
+            return Err(
+                input.error("expected element `<Sequence> ... </Sequence>` or `{ composite }`")
+            );
+        }
+        input.parse::<Token![<]>()?;
+        let kind = input.parse::<Ident>()?;
+
+        let mut attrs: Vec<Attr> = vec![];
+        while !input.peek(Token![>]) && !input.peek(Token![/]) {
+            // `do` is a keyword
+            let name = Ident::parse_any(input)
+                .map_err(|err| syn::Error::new(err.span(), "expected attribute, `>` or `/>`"))?;
+            if attrs.iter().any(|attr| attr.name == name) {
+                return Err(syn::Error::new(
+                    name.span(),
+                    format!("duplicate attribute `{name}`"),
+                ));
+            }
+            input.parse::<Token![=]>()?;
+            let value = input.parse()?;
+            attrs.push(Attr { name, value });
+        }
+
+        if input.peek(Token![/]) {
+            input.parse::<Token![/]>()?;
+            input.parse::<Token![>]>()?;
+            return Ok(Self::Element {
+                kind,
+                attrs,
+                childs: vec![],
+            });
+        }
+        input.parse::<Token![>]>()?;
+
+        let mut childs = vec![];
+        while !(input.peek(Token![<]) && input.peek2(Token![/])) {
+            if input.is_empty() {
+                return Err(syn::Error::new(
+                    kind.span(),
+                    format!("unclosed element, expected `</{kind}>`"),
+                ));
+            }
+            childs.push(input.parse()?);
+        }
+        input.parse::<Token![<]>()?;
+        input.parse::<Token![/]>()?;
+        let close = input.parse::<Ident>()?;
+        if close != kind {
+            return Err(syn::Error::new(
+                close.span(),
+                format!("expected `</{kind}>`, found `</{close}>`"),
+            ));
+        }
+        input.parse::<Token![>]>()?;
+        Ok(Self::Element {
+            kind,
+            attrs,
+            childs,
+        })
+    }
+}
+
+pub struct TreeData {
+    root: Node,
+}
+
+impl Parse for TreeData {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let root = input.parse()?;
+        if !input.is_empty() {
+            return Err(input.error("tree has only one root element"));
+        }
+        Ok(Self { root })
+    }
+}
+
+impl TreeData {
+    pub fn parse_token(&self) -> syn::Result<TokenStream2> {
+        self.root.composite(false)
+    }
+}
+
+/// Attributes of one element, taken as they are used
+struct Attrs<'a> {
+    kind: &'a Ident,
+    attrs: Vec<&'a Attr>,
+}
+
+impl<'a> Attrs<'a> {
+    fn take(&mut self, name: &str) -> Option<&'a AttrValue> {
+        let index = self.attrs.iter().position(|attr| attr.name == name)?;
+        Some(&self.attrs.remove(index).value)
+    }
+
+    fn require(&mut self, name: &str) -> syn::Result<&'a AttrValue> {
+        let kind = self.kind;
+        self.take(name).ok_or_else(|| {
+            syn::Error::new(kind.span(), format!("`{kind}` require attribute `{name}`"))
+        })
+    }
+
+    /// Error on first attribute nobody used
+    fn finish(&self) -> syn::Result<()> {
+        match self.attrs.first() {
+            Some(attr) => Err(syn::Error::new(
+                attr.name.span(),
+                format!("unknown attribute `{}` for `{}`", attr.name, self.kind),
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+const KINDS: &str = "Action, Sequence, PrioritySelector, RandomSequence, RandomSelector, \
+WeightedSelector, Decorator, DecoratorContinue, InterruptAction, Inverter, UntilSuccess, \
+UntilFailure, Timeout, Wait";
+
+impl Node {
+    /// Expression building Composite of this node.
+    /// `weighted`: node is child of WeightedSelector, it can have `weight`
+    fn composite(&self, weighted: bool) -> syn::Result<TokenStream2> {
+        let (kind, attrs, childs) = match self {
+            Self::Expr(expr) => return Ok(into_composite(expr)),
+            Self::Element {
+                kind,
+                attrs,
+                childs,
+            } => (kind, attrs, childs),
+        };
+        let mut attrs = Attrs {
+            kind,
+            attrs: attrs.iter().collect(),
+        };
+        let weighted_childs = kind == "WeightedSelector";
+        let childs = childs
+            .iter()
+            .map(|child| child.composite(weighted_childs))
+            .collect::<syn::Result<Vec<_>>>()?;
+        let name = attrs.take("name").map(AttrValue::to_tokens);
+        let timeout = attrs.take("timeout").map(AttrValue::duration).transpose()?;
+        if weighted {
+            // read by parent
+            attrs.take("weight");
+        }
+
+        let path = quote!(::bhv_async::common_behaviors);
+        let node = match kind.to_string().as_str() {
+            "Action" => {
+                expect_childs(kind, &childs, 0)?;
+                action(attrs.require("do")?)
+            }
+            "Sequence" | "PrioritySelector" => {
+                expect_some_childs(kind, &childs)?;
+                quote! {
+                    ::core::convert::Into::<::bhv_async::composite::Composite>::into(
+                        #path::#kind::new([#(#childs,)*])
+                    )
+                }
+            }
+            "RandomSequence" | "RandomSelector" | "WeightedSelector" => {
+                expect_some_childs(kind, &childs)?;
+                let childs = match self {
+                    Self::Element { childs: nodes, .. } if weighted_childs => {
+                        let weights = nodes.iter().map(weight);
+                        quote!([#(((#weights) as f32, #childs),)*])
+                    }
+                    _ => quote!([#(#childs,)*]),
+                };
+                let node = match attrs.take("seed") {
+                    Some(seed) => {
+                        let seed = seed.to_tokens();
+                        quote!(#path::#kind::with_seed(#childs, #seed))
+                    }
+                    None => quote!(#path::#kind::new(#childs)),
+                };
+                into_composite_tokens(node)
+            }
+            "Decorator" | "DecoratorContinue" | "InterruptAction" => {
+                expect_childs(kind, &childs, 1)?;
+                let condition = attrs.require("condition")?.to_tokens();
+                let child = &childs[0];
+                into_composite_tokens(quote!(#path::#kind::new(#condition, #child)))
+            }
+            "Inverter" => {
+                expect_childs(kind, &childs, 1)?;
+                let child = &childs[0];
+                into_composite_tokens(quote!(#path::#kind::new(#child)))
+            }
+            "UntilSuccess" | "UntilFailure" => {
+                expect_childs(kind, &childs, 1)?;
+                let child = &childs[0];
+                // same meaning as `#[retry]` / `#[repeat]` node attributes
+                let max_runs = match kind == "UntilSuccess" {
+                    true => attrs.take("retry").map(|retry| {
+                        let retry = retry.to_tokens();
+                        quote!((#retry) + 1)
+                    }),
+                    false => attrs.take("repeat").map(AttrValue::to_tokens),
+                };
+                let node = match max_runs {
+                    Some(max_runs) => quote!(#path::#kind::new(#child).with_max_runs(#max_runs)),
+                    None => quote!(#path::#kind::new(#child)),
+                };
+                into_composite_tokens(node)
+            }
+            "Timeout" => {
+                expect_childs(kind, &childs, 1)?;
+                let duration = attrs.require("duration")?.duration()?;
+                let child = &childs[0];
+                into_composite_tokens(quote!(#path::#kind::new(#duration, #child)))
+            }
+            "Wait" => {
+                expect_childs(kind, &childs, 0)?;
+                let duration = attrs.require("duration")?.duration()?;
+                into_composite_tokens(quote!(#path::#kind::new(#duration)))
+            }
+            _ => {
+                return Err(syn::Error::new(
+                    kind.span(),
+                    format!("unknown node `{kind}`, expected one of: {KINDS}"),
+                ))
+            }
+        };
+        attrs.finish()?;
+
+        let node = match name {
+            Some(name) => quote!(#node.with_name(#name)),
+            None => node,
+        };
+        Ok(match timeout {
+            Some(duration) => into_composite_tokens(quote! {
+                #path::Timeout::new(#duration, #node)
+            }),
+            None => node,
+        })
+    }
+}
+
+fn into_composite_tokens(node: TokenStream2) -> TokenStream2 {
+    quote! {
+        ::core::convert::Into::<::bhv_async::composite::Composite>::into(#node)
+    }
+}
+
+/// Weight of child in WeightedSelector, 1 by default
+fn weight(node: &Node) -> TokenStream2 {
+    let Node::Element { attrs, .. } = node else {
+        return quote!(1.0);
+    };
+    match attrs.iter().find(|attr| attr.name == "weight") {
+        Some(attr) => attr.value.to_tokens(),
+        None => quote!(1.0),
+    }
+}
+
+/// `do` of Action:
+/// - closure creating task, same as `Action! { ... }`
+/// - `async { ... }` block, run again each time
+/// - async function (or anything callable returning a future)
+fn action(value: &AttrValue) -> TokenStream2 {
+    let task = match value {
+        AttrValue::Expr(Expr::Closure(closure)) => {
+            return quote!(::bhv_async::macros::Action!(#closure))
+        }
+        AttrValue::Expr(Expr::Async(block)) => quote! {
+            move || ::std::boxed::Box::pin(#block) as ::bhv_async::composite::BoxAction
+        },
+        value => {
+            let function = value.to_tokens();
+            quote_spanned! {value.span()=>
+                move || ::std::boxed::Box::pin((#function)()) as ::bhv_async::composite::BoxAction
+            }
+        }
+    };
+    quote! {
+        ::bhv_async::composite::Composite::new_action(#task)
+    }
+}
+
+fn expect_childs(kind: &Ident, childs: &[TokenStream2], count: usize) -> syn::Result<()> {
+    if childs.len() == count {
+        return Ok(());
+    }
+    let message = match count {
+        0 => format!("`{kind}` can not have children, use `<{kind} .../>`"),
+        _ => format!("`{kind}` take exactly one child, found {}", childs.len()),
+    };
+    Err(syn::Error::new(kind.span(), message))
+}
+
+fn expect_some_childs(kind: &Ident, childs: &[TokenStream2]) -> syn::Result<()> {
+    if childs.is_empty() {
+        return Err(syn::Error::new(
+            kind.span(),
+            format!("`{kind}` need at least one child"),
+        ));
+    }
+    Ok(())
+}
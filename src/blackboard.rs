@@ -0,0 +1,42 @@
+use std::{
+    cell::{Ref, RefCell, RefMut},
+    rc::Rc,
+};
+
+/// Shared data of a tree (agent memory, world facts,...)
+/// Clone of blackboard point to same data, so you can capture it
+/// in as many action/condition closures as you want.
+#[derive(Default)]
+pub struct Blackboard<T>(Rc<RefCell<T>>);
+
+impl<T> Clone for Blackboard<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> Blackboard<T> {
+    pub fn new(value: T) -> Self {
+        Self(Rc::new(RefCell::new(value)))
+    }
+
+    /// Panic if data is currently mutable borrowed
+    pub fn get(&self) -> Ref<'_, T> {
+        self.0.borrow()
+    }
+
+    /// Panic if data is currently borrowed
+    pub fn get_mut(&self) -> RefMut<'_, T> {
+        self.0.borrow_mut()
+    }
+
+    /// Read data without holding the borrow
+    pub fn read<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        f(&self.0.borrow())
+    }
+
+    /// Change data without holding the borrow
+    pub fn update<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        f(&mut self.0.borrow_mut())
+    }
+}
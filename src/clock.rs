@@ -0,0 +1,301 @@
+use std::{
+    cell::RefCell,
+    cmp::Ordering,
+    collections::{BinaryHeap, HashSet},
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    sync::{Condvar, Mutex, OnceLock},
+    task::{Poll, Waker},
+    time::{Duration, Instant},
+};
+
+use crate::{composite::BoxAction, RunStatus};
+
+/// Source of time for time-based nodes (Wait, Timeout,...) and leaves.
+/// Time is a Duration since the clock epoch.
+pub trait Clock {
+    fn now(&self) -> Duration;
+
+    /// Wake task when clock reach deadline.
+    /// Wake immediately if deadline already passed, nothing to cancel then.
+    fn wake_at(&self, deadline: Duration, waker: &Waker) -> Option<TimerId>;
+
+    /// Forget a waker registered by `wake_at` before its deadline
+    fn cancel(&self, _timer: TimerId) {}
+}
+
+/// Waker registered to a clock, see `Clock::wake_at`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimerId(pub u64);
+
+/// Deadline of a clock with its waker registered,
+/// the waker is cancelled if dropped before deadline
+pub struct Timer {
+    clock: Rc<dyn Clock>,
+    deadline: Duration,
+    id: Option<TimerId>,
+}
+
+impl Timer {
+    pub fn start(clock: Rc<dyn Clock>, deadline: Duration, waker: &Waker) -> Self {
+        let id = clock.wake_at(deadline, waker);
+        Timer {
+            clock,
+            deadline,
+            id,
+        }
+    }
+
+    pub fn clock(&self) -> &Rc<dyn Clock> {
+        &self.clock
+    }
+
+    pub fn deadline(&self) -> Duration {
+        self.deadline
+    }
+
+    pub fn expired(&self) -> bool {
+        self.clock.now() >= self.deadline
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        if let Some(id) = self.id {
+            if !self.expired() {
+                self.clock.cancel(id);
+            }
+        }
+    }
+}
+
+/// Real time clock, no runtime needed:
+/// wakers are woken by a background timer thread.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+fn epoch() -> Instant {
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+    *EPOCH.get_or_init(Instant::now)
+}
+
+struct Entry {
+    deadline: Instant,
+    id: u64,
+    waker: Waker,
+}
+
+/// Earliest deadline first in BinaryHeap
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (other.deadline, other.id).cmp(&(self.deadline, self.id))
+    }
+}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Entry {}
+
+#[derive(Default)]
+struct Timers {
+    heap: BinaryHeap<Entry>,
+    next_id: u64,
+    /// Ids still in heap but not to wake
+    cancelled: HashSet<u64>,
+}
+
+#[derive(Default)]
+struct TimerQueue {
+    timers: Mutex<Timers>,
+    changed: Condvar,
+}
+
+fn timer_queue() -> &'static TimerQueue {
+    static QUEUE: OnceLock<TimerQueue> = OnceLock::new();
+    QUEUE.get_or_init(|| {
+        std::thread::Builder::new()
+            .name("bhv-async-timer".into())
+            .spawn(run_timers)
+            .expect("spawn timer thread");
+        TimerQueue::default()
+    })
+}
+
+fn run_timers() {
+    let queue = timer_queue();
+    let mut timers = queue.timers.lock().unwrap();
+    loop {
+        let now = Instant::now();
+        while timers
+            .heap
+            .peek()
+            .is_some_and(|entry| entry.deadline <= now)
+        {
+            let entry = timers.heap.pop().unwrap();
+            if !timers.cancelled.remove(&entry.id) {
+                entry.waker.wake();
+            }
+        }
+
+        timers = match timers.heap.peek().map(|entry| entry.deadline) {
+            Some(next) => queue.changed.wait_timeout(timers, next - now).unwrap().0,
+            None => queue.changed.wait(timers).unwrap(),
+        };
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        epoch().elapsed()
+    }
+
+    fn wake_at(&self, deadline: Duration, waker: &Waker) -> Option<TimerId> {
+        if deadline <= self.now() {
+            waker.wake_by_ref();
+            return None;
+        }
+        let queue = timer_queue();
+        let mut timers = queue.timers.lock().unwrap();
+        timers.next_id += 1;
+        let id = timers.next_id;
+        let deadline = epoch() + deadline;
+        // timer thread only need to know about a new earliest deadline
+        let earliest = timers
+            .heap
+            .peek()
+            .is_none_or(|entry| deadline < entry.deadline);
+        timers.heap.push(Entry {
+            deadline,
+            id,
+            waker: waker.clone(),
+        });
+        drop(timers);
+        if earliest {
+            queue.changed.notify_one();
+        }
+        Some(TimerId(id))
+    }
+
+    fn cancel(&self, timer: TimerId) {
+        let mut timers = timer_queue().timers.lock().unwrap();
+        let timers = &mut *timers;
+        timers.cancelled.insert(timer.0);
+        // drop cancelled entries once they are half of the queue,
+        // keeps the queue bounded by timers actually waiting
+        if timers.cancelled.len() * 2 > timers.heap.len() {
+            let cancelled = &timers.cancelled;
+            timers.heap.retain(|entry| !cancelled.contains(&entry.id));
+            timers.cancelled.clear();
+        }
+    }
+}
+
+thread_local! {
+    /// Clock of the scope being polled
+    static CURRENT: RefCell<Option<Rc<dyn Clock>>> = const { RefCell::new(None) };
+    /// Shared so time-based nodes does not allocate a clock each run
+    static SYSTEM: Rc<dyn Clock> = Rc::new(SystemClock);
+}
+
+/// Clock used by time-based nodes polled on this thread,
+/// SystemClock unless task is run by `scoped`
+pub fn current() -> Rc<dyn Clock> {
+    CURRENT
+        .with(|current| current.borrow().clone())
+        .unwrap_or_else(|| SYSTEM.with(Rc::clone))
+}
+
+/// Current time of current clock
+pub fn now() -> Duration {
+    current().now()
+}
+
+/// Run task with clock as current clock
+/// (test executor use it to give a virtual clock to whole tree)
+pub fn scoped(clock: Rc<dyn Clock>, task: BoxAction) -> BoxAction {
+    Box::pin(Scoped { clock, task })
+}
+
+struct Scoped {
+    clock: Rc<dyn Clock>,
+    task: BoxAction,
+}
+
+/// Restore outer clock even if poll panic
+struct ClockGuard(Option<Rc<dyn Clock>>);
+
+impl Drop for ClockGuard {
+    fn drop(&mut self) {
+        let outer = self.0.take();
+        CURRENT.with(|current| *current.borrow_mut() = outer);
+    }
+}
+
+impl Future for Scoped {
+    type Output = RunStatus;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
+        let outer = CURRENT.with(|current| current.borrow_mut().replace(self.clock.clone()));
+        let _guard = ClockGuard(outer);
+        self.task.as_mut().poll(cx)
+    }
+}
+
+/// Wait for duration of current clock, start counting at first poll
+///
+/// Action! {
+///     || async {
+///         bhv_async::clock::sleep(Duration::from_secs(1)).await;
+///         RunStatus::Success
+///     }
+/// }
+pub fn sleep(duration: Duration) -> Sleep {
+    Sleep {
+        duration,
+        timer: None,
+    }
+}
+
+/// Future of `sleep`, dropping it before deadline cancel its timer
+pub struct Sleep {
+    duration: Duration,
+    timer: Option<Timer>,
+}
+
+impl Sleep {
+    /// Clock and deadline, known after first poll
+    pub(crate) fn deadline(&self) -> Option<(&Rc<dyn Clock>, Duration)> {
+        self.timer
+            .as_ref()
+            .map(|timer| (timer.clock(), timer.deadline()))
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
+        // wake once at deadline, later polls only check it
+        let duration = self.duration;
+        let timer = self.timer.get_or_insert_with(|| {
+            let clock = current();
+            let deadline = clock.now() + duration;
+            Timer::start(clock, deadline, cx.waker())
+        });
+        if timer.expired() {
+            return Poll::Ready(());
+        }
+        Poll::Pending
+    }
+}
@@ -1,45 +1,75 @@
-use std::{future::Future, pin::Pin, rc::Rc, task::Poll};
+use std::{cell::RefCell, future::Future, pin::Pin, rc::Rc, task::Poll, time::Duration};
 
 use crate::{
-    composite::{BoxAction, Composite},
-    RunStatus,
+    clock,
+    composite::{ChildTask, Composite, Reset},
+    persist, RunStatus,
 };
 
+/// Default number of children a node can go through in one poll (see `with_step_limit`)
+pub const DEFAULT_STEP_LIMIT: usize = 32;
+
+/// Children finishing in same poll before node yield to executor
+#[derive(Clone, Copy)]
+struct StepLimit(usize);
+
+impl Default for StepLimit {
+    fn default() -> Self {
+        Self(DEFAULT_STEP_LIMIT)
+    }
+}
+
 /// An group action execute each branch of logic, in order.
 /// If all branches succeed, this composite will return a successful run status.
 /// If any branch fails, this composite will return a failed run status.
 #[derive(Default)]
 pub struct Sequence {
-    childs: Vec<Composite>,
+    childs: Rc<[Composite]>,
     index: usize,
-    fut: Option<BoxAction>,
+    /// Task per child, reusable ones are kept for next run
+    tasks: Vec<ChildTask>,
+    step_limit: StepLimit,
 }
 
 impl Clone for Sequence {
     fn clone(&self) -> Self {
         Self {
             childs: self.childs.clone(),
+            step_limit: self.step_limit,
             ..Default::default()
         }
     }
 }
 
-IMPLEMENT_INTO_COMPOSITE!(Sequence);
+IMPLEMENT_INTO_COMPOSITE!(Sequence, reusable);
 
 impl Sequence {
-    pub fn new(childs: impl Into<Vec<Composite>>) -> Self {
+    pub fn new(childs: impl Into<Rc<[Composite]>>) -> Self {
         Self {
             childs: childs.into(),
             ..Default::default()
         }
     }
+
+    /// Children finishing immediately are run in same poll,
+    /// up to limit then yield to executor (1 = yield after each child)
+    pub fn with_step_limit(mut self, limit: usize) -> Self {
+        self.step_limit = StepLimit(limit.max(1));
+        self
+    }
+}
+
+impl Reset for Sequence {
+    fn reset(&mut self) {
+        self.index = 0;
+    }
 }
 
 /// Those children composite will not break composite Selector
 /// Composite Selector: PrioritySelector,...
 /// maybe export api for add more selector
 /// => maybe should use "static mut"
-const OPTIONAL_CHILD_NAMES: [&str; 1] = ["DecoratorContinue"];
+const OPTIONAL_CHILD_KINDS: [&str; 1] = ["DecoratorContinue"];
 
 impl Future for Sequence {
     type Output = RunStatus;
@@ -50,40 +80,47 @@ impl Future for Sequence {
             return Poll::Ready(RunStatus::Success);
         }
 
-        if this.fut.is_none() {
-            let index = this.index;
-            let child = &this.childs[index];
-            println!(
-                "Running composite name: {} ({}/{})",
-                child.name,
-                index + 1,
-                this.childs.len()
-            );
-            let fut = (child.task_production)();
-            this.fut = Some(fut);
+        if this.tasks.is_empty() {
+            this.tasks.resize_with(this.childs.len(), Default::default);
         }
-
-        // let fut = this.fut.as_mut().unwrap();
-        match Pin::new(this.fut.as_mut().unwrap()).poll(cx) {
-            Poll::Ready(status) => {
-                if status == RunStatus::Failure {
-                    return Poll::Ready(RunStatus::Failure);
+        // go through children finishing immediately without executor round-trip
+        for _ in 0..this.step_limit.0 {
+            if !this.tasks[this.index].is_running() {
+                if this.index == 0 {
+                    if let Some(index) = persist::restore_index() {
+                        this.index = index.min(this.childs.len() - 1);
+                    }
                 }
-                if this.index + 1 >= this.childs.len() {
-                    return Poll::Ready(RunStatus::Success);
-                }
-                this.index += 1;
-                this.fut.take();
+                let index = this.index;
+                persist::save_index(index);
+                let child = &this.childs[index];
+                println!(
+                    "Running composite name: {} ({}/{})",
+                    child.label(),
+                    index + 1,
+                    this.childs.len()
+                );
+                this.tasks[index].start(child, Some(index));
+            }
 
-                // NOTE
-                // When context poll done an task. it will no longer getting poll
-                // we need notifiy executor we have more task need scheduling.
-                // call wake_by_ref will make it work.
-                cx.waker().wake_by_ref();
-                Poll::Pending
+            let Poll::Ready(status) = this.tasks[this.index].poll(cx) else {
+                return Poll::Pending;
+            };
+            if status == RunStatus::Failure {
+                return Poll::Ready(RunStatus::Failure);
             }
-            Poll::Pending => Poll::Pending,
+            if this.index + 1 >= this.childs.len() {
+                return Poll::Ready(RunStatus::Success);
+            }
+            this.index += 1;
         }
+
+        // NOTE
+        // When context poll done an task. it will no longer getting poll
+        // we need notifiy executor we have more task need scheduling.
+        // call wake_by_ref will make it work.
+        cx.waker().wake_by_ref();
+        Poll::Pending
     }
 }
 
@@ -93,28 +130,45 @@ impl Future for Sequence {
 /// This composite type is Selector
 #[derive(Default)]
 pub struct PrioritySelector {
-    childs: Vec<Composite>,
+    childs: Rc<[Composite]>,
     index: usize,
     is_running_optional_child: bool,
-    fut: Option<BoxAction>,
+    /// Task per child, reusable ones are kept for next run
+    tasks: Vec<ChildTask>,
+    step_limit: StepLimit,
 }
 
 impl Clone for PrioritySelector {
     fn clone(&self) -> Self {
         Self {
             childs: self.childs.clone(),
+            step_limit: self.step_limit,
             ..Default::default()
         }
     }
 }
 
 impl PrioritySelector {
-    pub fn new(childs: impl Into<Vec<Composite>>) -> Self {
+    pub fn new(childs: impl Into<Rc<[Composite]>>) -> Self {
         Self {
             childs: childs.into(),
             ..Default::default()
         }
     }
+
+    /// Children finishing immediately are run in same poll,
+    /// up to limit then yield to executor (1 = yield after each child)
+    pub fn with_step_limit(mut self, limit: usize) -> Self {
+        self.step_limit = StepLimit(limit.max(1));
+        self
+    }
+}
+
+impl Reset for PrioritySelector {
+    fn reset(&mut self) {
+        self.index = 0;
+        self.is_running_optional_child = false;
+    }
 }
 
 impl Future for PrioritySelector {
@@ -126,75 +180,331 @@ impl Future for PrioritySelector {
             return Poll::Ready(RunStatus::Failure);
         }
 
-        if this.fut.is_none() {
-            let index = this.index;
-            let child = &this.childs[index];
-            println!(
-                "Running composite name: {} ({}/{})",
-                child.name,
-                index + 1,
-                this.childs.len()
-            );
-            let fut = (child.task_production)();
-            this.fut = Some(fut);
-            this.is_running_optional_child = OPTIONAL_CHILD_NAMES.contains(&&*child.name);
+        if this.tasks.is_empty() {
+            this.tasks.resize_with(this.childs.len(), Default::default);
         }
+        // go through children finishing immediately without executor round-trip
+        for _ in 0..this.step_limit.0 {
+            if !this.tasks[this.index].is_running() {
+                if this.index == 0 {
+                    if let Some(index) = persist::restore_index() {
+                        this.index = index.min(this.childs.len() - 1);
+                    }
+                }
+                let index = this.index;
+                persist::save_index(index);
+                let child = &this.childs[index];
+                println!(
+                    "Running composite name: {} ({}/{})",
+                    child.label(),
+                    index + 1,
+                    this.childs.len()
+                );
+                this.tasks[index].start(child, Some(index));
+                this.is_running_optional_child = OPTIONAL_CHILD_KINDS.contains(&child.kind);
+            }
 
-        // let fut = this.fut.as_mut().unwrap();
+            let Poll::Ready(mut status) = this.tasks[this.index].poll(cx) else {
+                return Poll::Pending;
+            };
+            if this.is_running_optional_child {
+                // overwrite status to failure if running Optional child
+                status = RunStatus::Failure;
+            }
 
-        match Pin::new(this.fut.as_mut().unwrap()).poll(cx) {
-            Poll::Ready(mut status) => {
-                if this.is_running_optional_child {
-                    // overwrite status to failure if running Optional child
-                    status = RunStatus::Failure;
-                }
+            if status == RunStatus::Success {
+                return Poll::Ready(RunStatus::Success);
+            }
+            if status == RunStatus::Failure && this.index + 1 >= this.childs.len() {
+                return Poll::Ready(RunStatus::Failure);
+            }
+            this.index += 1;
+        }
 
-                if status == RunStatus::Success {
-                    return Poll::Ready(RunStatus::Success);
-                }
-                if status == RunStatus::Failure && this.index + 1 >= this.childs.len() {
-                    return Poll::Ready(RunStatus::Failure);
-                }
-                this.index += 1;
-                this.fut.take();
+        // NOTE
+        // When context poll done an task. it will no longer getting poll
+        // we need notifiy executor we have more task need scheduling.
+        // call wake_by_ref will make it work.
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
 
-                // NOTE
-                // When context poll done an task. it will no longer getting poll
-                // we need notifiy executor we have more task need scheduling.
-                // call wake_by_ref will make it work.
-                cx.waker().wake_by_ref();
-                Poll::Pending
+IMPLEMENT_INTO_COMPOSITE!(PrioritySelector, reusable);
+
+/// Random source used by random composites.
+/// Clone of node share same generator, so every run get new roll
+/// and seeded generator still give same result for same tree.
+type SharedRng = Rc<RefCell<fastrand::Rng>>;
+
+/// Random order of children, or the saved one when tree is resumed
+fn shuffled_order(rng: &SharedRng, len: usize) -> Vec<usize> {
+    let order = persist::restore_order()
+        .filter(|order| order.len() == len)
+        .unwrap_or_else(|| {
+            let mut order: Vec<_> = (0..len).collect();
+            rng.borrow_mut().shuffle(&mut order);
+            order
+        });
+    persist::save_order(&order);
+    order
+}
+
+/// An Selector like PrioritySelector but children order is shuffled each run.
+/// It execute each branch in this random order until one succeeds.
+///
+/// This composite type is Selector
+pub struct RandomSelector {
+    childs: Rc<[Composite]>,
+    rng: SharedRng,
+    inner: Option<PrioritySelector>,
+}
+
+impl Clone for RandomSelector {
+    fn clone(&self) -> Self {
+        Self {
+            childs: self.childs.clone(),
+            rng: self.rng.clone(),
+            inner: None,
+        }
+    }
+}
+
+impl RandomSelector {
+    pub fn new(childs: impl Into<Rc<[Composite]>>) -> Self {
+        Self::with_rng(childs, fastrand::Rng::new())
+    }
+
+    /// Seeded generator make the order deterministic (useful for test)
+    pub fn with_seed(childs: impl Into<Rc<[Composite]>>, seed: u64) -> Self {
+        Self::with_rng(childs, fastrand::Rng::with_seed(seed))
+    }
+
+    pub fn with_rng(childs: impl Into<Rc<[Composite]>>, rng: fastrand::Rng) -> Self {
+        Self {
+            childs: childs.into(),
+            rng: Rc::new(RefCell::new(rng)),
+            inner: None,
+        }
+    }
+
+    fn shuffled(&self) -> Vec<Composite> {
+        let order = shuffled_order(&self.rng, self.childs.len());
+        order
+            .iter()
+            .map(|&index| self.childs[index].clone())
+            .collect()
+    }
+}
+
+impl Future for RandomSelector {
+    type Output = RunStatus;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
+        if self.inner.is_none() {
+            let childs = self.shuffled();
+            self.inner = Some(PrioritySelector::new(childs));
+        }
+        Pin::new(self.inner.as_mut().unwrap()).poll(cx)
+    }
+}
+
+IMPLEMENT_INTO_COMPOSITE!(RandomSelector);
+
+/// An group action like Sequence but children order is shuffled each run.
+/// If all branches succeed, this composite will return a successful run status.
+/// If any branch fails, this composite will return a failed run status.
+pub struct RandomSequence {
+    childs: Rc<[Composite]>,
+    rng: SharedRng,
+    inner: Option<Sequence>,
+}
+
+impl Clone for RandomSequence {
+    fn clone(&self) -> Self {
+        Self {
+            childs: self.childs.clone(),
+            rng: self.rng.clone(),
+            inner: None,
+        }
+    }
+}
+
+impl RandomSequence {
+    pub fn new(childs: impl Into<Rc<[Composite]>>) -> Self {
+        Self::with_rng(childs, fastrand::Rng::new())
+    }
+
+    /// Seeded generator make the order deterministic (useful for test)
+    pub fn with_seed(childs: impl Into<Rc<[Composite]>>, seed: u64) -> Self {
+        Self::with_rng(childs, fastrand::Rng::with_seed(seed))
+    }
+
+    pub fn with_rng(childs: impl Into<Rc<[Composite]>>, rng: fastrand::Rng) -> Self {
+        Self {
+            childs: childs.into(),
+            rng: Rc::new(RefCell::new(rng)),
+            inner: None,
+        }
+    }
+
+    fn shuffled(&self) -> Vec<Composite> {
+        let order = shuffled_order(&self.rng, self.childs.len());
+        order
+            .iter()
+            .map(|&index| self.childs[index].clone())
+            .collect()
+    }
+}
+
+impl Future for RandomSequence {
+    type Output = RunStatus;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
+        if self.inner.is_none() {
+            let childs = self.shuffled();
+            self.inner = Some(Sequence::new(childs));
+        }
+        Pin::new(self.inner.as_mut().unwrap()).poll(cx)
+    }
+}
+
+IMPLEMENT_INTO_COMPOSITE!(RandomSequence);
+
+/// An Selector pick only one child by weight then return its status.
+/// Child with higher weight get picked more often,
+/// weight <= 0, NaN or infinite never get picked.
+/// If no child can be picked, return failed.
+pub struct WeightedSelector {
+    childs: Rc<[(f32, Composite)]>,
+    rng: SharedRng,
+    /// Picked child of current run
+    index: Option<usize>,
+    tasks: Vec<ChildTask>,
+}
+
+impl Clone for WeightedSelector {
+    fn clone(&self) -> Self {
+        Self {
+            childs: self.childs.clone(),
+            rng: self.rng.clone(),
+            index: None,
+            tasks: Vec::new(),
+        }
+    }
+}
+
+impl WeightedSelector {
+    pub fn new(childs: impl Into<Rc<[(f32, Composite)]>>) -> Self {
+        Self::with_rng(childs, fastrand::Rng::new())
+    }
+
+    /// Seeded generator make the pick deterministic (useful for test)
+    pub fn with_seed(childs: impl Into<Rc<[(f32, Composite)]>>, seed: u64) -> Self {
+        Self::with_rng(childs, fastrand::Rng::with_seed(seed))
+    }
+
+    pub fn with_rng(childs: impl Into<Rc<[(f32, Composite)]>>, rng: fastrand::Rng) -> Self {
+        Self {
+            childs: childs.into(),
+            rng: Rc::new(RefCell::new(rng)),
+            index: None,
+            tasks: Vec::new(),
+        }
+    }
+
+    /// Index of picked child
+    fn pick(&self) -> Option<usize> {
+        let weights = self
+            .childs
+            .iter()
+            .map(|(weight, _)| *weight)
+            .filter(|weight| can_pick(*weight));
+        let total: f32 = weights.sum();
+        if !can_pick(total) {
+            return None;
+        }
+
+        let mut roll = self.rng.borrow_mut().f32() * total;
+        let mut picked = None;
+        for (index, (weight, _)) in self.childs.iter().enumerate() {
+            if !can_pick(*weight) {
+                continue;
             }
-            Poll::Pending => Poll::Pending,
+            picked = Some(index);
+            if roll < *weight {
+                break;
+            }
+            roll -= weight;
         }
+        picked
+    }
+}
+
+fn can_pick(weight: f32) -> bool {
+    weight.is_finite() && weight > 0.0
+}
+
+impl Future for WeightedSelector {
+    type Output = RunStatus;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let index = match this.index {
+            Some(index) => index,
+            None => {
+                let restored = persist::restore_index().filter(|index| *index < this.childs.len());
+                let Some(index) = restored.or_else(|| this.pick()) else {
+                    return Poll::Ready(RunStatus::Failure);
+                };
+                persist::save_index(index);
+                let child = &this.childs[index].1;
+                #[cfg(feature = "tracing")]
+                tracing::debug!(child = %child.label(), index, "weighted pick");
+                if this.tasks.is_empty() {
+                    this.tasks.resize_with(this.childs.len(), Default::default);
+                }
+                this.tasks[index].start(child, Some(index));
+                this.index = Some(index);
+                index
+            }
+        };
+        this.tasks[index].poll(cx)
+    }
+}
+
+impl Reset for WeightedSelector {
+    fn reset(&mut self) {
+        self.index = None;
     }
 }
 
-IMPLEMENT_INTO_COMPOSITE!(PrioritySelector);
+IMPLEMENT_INTO_COMPOSITE!(WeightedSelector, reusable);
 
 /// A decorator that allows you to execute code only if some condition is met.
 /// Otherwise, return failed.
 pub struct Decorator {
     run_condition: Rc<dyn Fn() -> bool>,
     child: Composite,
-    fut: Option<BoxAction>,
+    task: ChildTask,
 }
 impl Clone for Decorator {
     fn clone(&self) -> Self {
         Self {
             run_condition: self.run_condition.clone(),
             child: self.child.clone(),
-            fut: None,
+            task: ChildTask::default(),
         }
     }
 }
 
 impl Decorator {
+    #[track_caller]
     pub fn new(condition: impl Fn() -> bool + 'static, child: impl Into<Composite>) -> Self {
         Self {
             run_condition: Rc::new(condition),
             child: child.into(),
-            fut: None,
+            task: ChildTask::default(),
         }
     }
 }
@@ -203,19 +513,23 @@ impl Future for Decorator {
     type Output = RunStatus;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
-        if !(self.run_condition)() {
+        let this = &mut *self;
+        if !(this.run_condition)() {
             return Poll::Ready(RunStatus::Failure);
         }
 
-        if self.fut.is_none() {
-            let fut = (self.child.task_production)();
-            self.fut = Some(fut);
-        }
-        Pin::new(self.fut.as_mut().unwrap()).poll(cx)
+        this.task.start(&this.child, Some(0));
+        this.task.poll(cx)
     }
 }
 
-IMPLEMENT_INTO_COMPOSITE!(Decorator);
+impl Reset for Decorator {
+    fn reset(&mut self) {
+        self.task.abort();
+    }
+}
+
+IMPLEMENT_INTO_COMPOSITE!(Decorator, reusable);
 
 /// A decorator that allows you to execute code only if some condition is met. It does not 'break' the current
 /// tree if the CONDITION FAILS, or CHILDREN FAIL.
@@ -226,7 +540,7 @@ IMPLEMENT_INTO_COMPOSITE!(Decorator);
 pub struct DecoratorContinue {
     run_condition: Rc<dyn Fn() -> bool>,
     child: Composite,
-    fut: Option<BoxAction>,
+    task: ChildTask,
 }
 
 impl Clone for DecoratorContinue {
@@ -234,17 +548,18 @@ impl Clone for DecoratorContinue {
         Self {
             run_condition: self.run_condition.clone(),
             child: self.child.clone(),
-            fut: None,
+            task: ChildTask::default(),
         }
     }
 }
 
 impl DecoratorContinue {
+    #[track_caller]
     pub fn new(condition: impl Fn() -> bool + 'static, child: impl Into<Composite>) -> Self {
         Self {
             run_condition: Rc::new(condition),
             child: child.into(),
-            fut: None,
+            task: ChildTask::default(),
         }
     }
 }
@@ -252,22 +567,26 @@ impl Future for DecoratorContinue {
     type Output = RunStatus;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
-        if !(self.run_condition)() {
+        let this = &mut *self;
+        if !(this.run_condition)() {
             return Poll::Ready(RunStatus::Success);
         }
 
-        if self.fut.is_none() {
-            let fut = (self.child.task_production)();
-            self.fut = Some(fut);
-        }
-        match Pin::new(self.fut.as_mut().unwrap()).poll(cx) {
+        this.task.start(&this.child, Some(0));
+        match this.task.poll(cx) {
             Poll::Ready(_) => Poll::Ready(RunStatus::Success),
             Poll::Pending => Poll::Pending,
         }
     }
 }
 
-IMPLEMENT_INTO_COMPOSITE!(DecoratorContinue);
+impl Reset for DecoratorContinue {
+    fn reset(&mut self) {
+        self.task.abort();
+    }
+}
+
+IMPLEMENT_INTO_COMPOSITE!(DecoratorContinue, reusable);
 
 /// An action running with check condition between Poll
 /// if condition not met.. it will return Success immediately (stop action with success status)
@@ -275,7 +594,7 @@ IMPLEMENT_INTO_COMPOSITE!(DecoratorContinue);
 pub struct InterruptAction {
     run_condition: Rc<dyn Fn() -> bool>,
     child: Composite,
-    fut: Option<BoxAction>,
+    task: ChildTask,
 }
 
 impl Clone for InterruptAction {
@@ -283,17 +602,18 @@ impl Clone for InterruptAction {
         Self {
             run_condition: self.run_condition.clone(),
             child: self.child.clone(),
-            fut: None,
+            task: ChildTask::default(),
         }
     }
 }
 
 impl InterruptAction {
+    #[track_caller]
     pub fn new(condition: impl Fn() -> bool + 'static, child: impl Into<Composite>) -> Self {
         Self {
             run_condition: Rc::new(condition),
             child: child.into(),
-            fut: None,
+            task: ChildTask::default(),
         }
     }
 }
@@ -302,14 +622,12 @@ impl Future for InterruptAction {
     type Output = RunStatus;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
-        if self.fut.is_none() {
-            let fut = (self.child.task_production)();
-            self.fut = Some(fut);
-        }
-        match Pin::new(self.fut.as_mut().unwrap()).poll(cx) {
+        let this = &mut *self;
+        this.task.start(&this.child, Some(0));
+        match this.task.poll(cx) {
             Poll::Ready(status) => Poll::Ready(status),
             Poll::Pending => {
-                if !(self.run_condition)() {
+                if !(this.run_condition)() {
                     println!("Trigger interrupt");
                     return Poll::Ready(RunStatus::Failure);
                 }
@@ -320,30 +638,37 @@ impl Future for InterruptAction {
     }
 }
 
-IMPLEMENT_INTO_COMPOSITE!(InterruptAction);
+impl Reset for InterruptAction {
+    fn reset(&mut self) {
+        self.task.abort();
+    }
+}
+
+IMPLEMENT_INTO_COMPOSITE!(InterruptAction, reusable);
 
 /// Run action then return status Inverter
 /// Success became Failed
 /// Failed became Success
 pub struct Inverter {
     child: Composite,
-    fut: Option<BoxAction>,
+    task: ChildTask,
 }
 
 impl Clone for Inverter {
     fn clone(&self) -> Self {
         Self {
             child: self.child.clone(),
-            fut: None,
+            task: ChildTask::default(),
         }
     }
 }
 
 impl Inverter {
+    #[track_caller]
     pub fn new(child: impl Into<Composite>) -> Self {
         Self {
             child: child.into(),
-            fut: None,
+            task: ChildTask::default(),
         }
     }
 }
@@ -352,11 +677,9 @@ impl Future for Inverter {
     type Output = RunStatus;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
-        if self.fut.is_none() {
-            let fut = (self.child.task_production)();
-            self.fut = Some(fut);
-        }
-        match Pin::new(self.fut.as_mut().unwrap()).poll(cx) {
+        let this = &mut *self;
+        this.task.start(&this.child, Some(0));
+        match this.task.poll(cx) {
             Poll::Ready(status) => match status {
                 RunStatus::Success => Poll::Ready(RunStatus::Failure),
                 RunStatus::Failure => Poll::Ready(RunStatus::Success),
@@ -366,102 +689,320 @@ impl Future for Inverter {
     }
 }
 
-IMPLEMENT_INTO_COMPOSITE!(Inverter);
+impl Reset for Inverter {
+    fn reset(&mut self) {
+        self.task.abort();
+    }
+}
+
+IMPLEMENT_INTO_COMPOSITE!(Inverter, reusable);
 
 /// Run action until it success
 /// Mean ignore failure, recreate and run again until success
 pub struct UntilSuccess {
     child: Composite,
-    fut: Option<BoxAction>,
+    task: ChildTask,
+    step_limit: StepLimit,
+    max_runs: Option<usize>,
+    runs: usize,
 }
 
 impl Clone for UntilSuccess {
     fn clone(&self) -> Self {
         Self {
             child: self.child.clone(),
-            fut: None,
+            task: ChildTask::default(),
+            step_limit: self.step_limit,
+            max_runs: self.max_runs,
+            runs: 0,
         }
     }
 }
 
 impl UntilSuccess {
+    #[track_caller]
     pub fn new(child: impl Into<Composite>) -> Self {
         Self {
             child: child.into(),
-            fut: None,
+            task: ChildTask::default(),
+            step_limit: StepLimit::default(),
+            max_runs: None,
+            runs: 0,
         }
     }
+
+    /// Runs finishing immediately are retried in same poll,
+    /// up to limit then yield to executor (1 = yield after each run)
+    pub fn with_step_limit(mut self, limit: usize) -> Self {
+        self.step_limit = StepLimit(limit.max(1));
+        self
+    }
+
+    /// Give up after count runs, returning Failure of last run
+    pub fn with_max_runs(mut self, count: usize) -> Self {
+        self.max_runs = Some(count.max(1));
+        self
+    }
 }
 
 impl Future for UntilSuccess {
     type Output = RunStatus;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
-        if self.fut.is_none() {
-            let fut = (self.child.task_production)();
-            self.fut = Some(fut);
-        }
-        match Pin::new(self.fut.as_mut().unwrap()).poll(cx) {
-            Poll::Ready(status) => {
-                if status == RunStatus::Failure {
-                    self.fut.take();
-                    cx.waker().wake_by_ref();
-                    Poll::Pending
-                } else {
-                    Poll::Ready(status)
+        let this = &mut *self;
+        if this.runs == 0 && !this.task.is_running() {
+            if let Some(runs) = persist::restore_runs() {
+                this.runs = runs;
+            }
+        }
+        for _ in 0..this.step_limit.0 {
+            this.task.start(&this.child, Some(0));
+            match this.task.poll(cx) {
+                Poll::Ready(RunStatus::Failure) => {
+                    this.runs += 1;
+                    // only bounded repeats need to know runs done
+                    if this.max_runs.is_some() {
+                        persist::save_runs(this.runs);
+                    }
+                    if this.max_runs == Some(this.runs) {
+                        return Poll::Ready(RunStatus::Failure);
+                    }
                 }
+                Poll::Ready(status) => return Poll::Ready(status),
+                Poll::Pending => return Poll::Pending,
             }
-            Poll::Pending => Poll::Pending,
         }
+        cx.waker().wake_by_ref();
+        Poll::Pending
     }
 }
 
-IMPLEMENT_INTO_COMPOSITE!(UntilSuccess);
+impl Reset for UntilSuccess {
+    fn reset(&mut self) {
+        self.task.abort();
+        self.runs = 0;
+    }
+}
+
+IMPLEMENT_INTO_COMPOSITE!(UntilSuccess, reusable);
 
 /// Run action until it failure
 /// Mean ignore success, recreate and run again until failure
 pub struct UntilFailure {
     child: Composite,
-    fut: Option<BoxAction>,
+    task: ChildTask,
+    step_limit: StepLimit,
+    max_runs: Option<usize>,
+    runs: usize,
 }
 impl Clone for UntilFailure {
     fn clone(&self) -> Self {
         Self {
             child: self.child.clone(),
-            fut: None,
+            task: ChildTask::default(),
+            step_limit: self.step_limit,
+            max_runs: self.max_runs,
+            runs: 0,
         }
     }
 }
 
 impl UntilFailure {
+    #[track_caller]
     pub fn new(child: impl Into<Composite>) -> Self {
         Self {
             child: child.into(),
-            fut: None,
+            task: ChildTask::default(),
+            step_limit: StepLimit::default(),
+            max_runs: None,
+            runs: 0,
         }
     }
+
+    /// Runs finishing immediately are retried in same poll,
+    /// up to limit then yield to executor (1 = yield after each run)
+    pub fn with_step_limit(mut self, limit: usize) -> Self {
+        self.step_limit = StepLimit(limit.max(1));
+        self
+    }
+
+    /// Stop after count runs, returning Success of last run
+    pub fn with_max_runs(mut self, count: usize) -> Self {
+        self.max_runs = Some(count.max(1));
+        self
+    }
 }
 impl Future for UntilFailure {
     type Output = RunStatus;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
-        if self.fut.is_none() {
-            let fut = (self.child.task_production)();
-            self.fut = Some(fut);
-        }
-        match Pin::new(self.fut.as_mut().unwrap()).poll(cx) {
-            Poll::Ready(status) => {
-                if status == RunStatus::Success {
-                    self.fut.take();
-                    cx.waker().wake_by_ref();
-                    Poll::Pending
-                } else {
-                    Poll::Ready(status)
+        let this = &mut *self;
+        if this.runs == 0 && !this.task.is_running() {
+            if let Some(runs) = persist::restore_runs() {
+                this.runs = runs;
+            }
+        }
+        for _ in 0..this.step_limit.0 {
+            this.task.start(&this.child, Some(0));
+            match this.task.poll(cx) {
+                Poll::Ready(RunStatus::Success) => {
+                    this.runs += 1;
+                    // only bounded repeats need to know runs done
+                    if this.max_runs.is_some() {
+                        persist::save_runs(this.runs);
+                    }
+                    if this.max_runs == Some(this.runs) {
+                        return Poll::Ready(RunStatus::Success);
+                    }
                 }
+                Poll::Ready(status) => return Poll::Ready(status),
+                Poll::Pending => return Poll::Pending,
             }
-            Poll::Pending => Poll::Pending,
         }
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+impl Reset for UntilFailure {
+    fn reset(&mut self) {
+        self.task.abort();
+        self.runs = 0;
+    }
+}
+
+IMPLEMENT_INTO_COMPOSITE!(UntilFailure, reusable);
+
+/// Wait for duration then return success
+/// Time is read from current clock (see `clock::scoped`), start counting at first poll
+pub struct Wait {
+    duration: Duration,
+    sleep: Option<clock::Sleep>,
+}
+
+impl Clone for Wait {
+    fn clone(&self) -> Self {
+        Self {
+            duration: self.duration,
+            sleep: None,
+        }
+    }
+}
+
+impl Wait {
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            duration,
+            sleep: None,
+        }
+    }
+}
+
+impl Future for Wait {
+    type Output = RunStatus;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
+        let duration = self.duration;
+        let sleep = self
+            .sleep
+            .get_or_insert_with(|| clock::sleep(persist::restore_remaining().unwrap_or(duration)));
+        match Pin::new(&mut *sleep).poll(cx) {
+            Poll::Ready(()) => Poll::Ready(RunStatus::Success),
+            Poll::Pending => {
+                if let Some((clock, deadline)) = sleep.deadline() {
+                    persist::save_deadline(clock, deadline);
+                }
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Reset for Wait {
+    fn reset(&mut self) {
+        self.sleep = None;
+    }
+}
+
+IMPLEMENT_INTO_COMPOSITE!(Wait, reusable);
+
+/// Run action with time limit
+/// if it not finish before duration, it is dropped and return failed.
+/// Otherwise, it will return action status
+/// Time is read from current clock (see `clock::scoped`), start counting at first poll
+pub struct Timeout {
+    duration: Duration,
+    child: Composite,
+    task: ChildTask,
+    deadline: Option<clock::Timer>,
+}
+
+impl Clone for Timeout {
+    fn clone(&self) -> Self {
+        Self {
+            duration: self.duration,
+            child: self.child.clone(),
+            task: ChildTask::default(),
+            deadline: None,
+        }
+    }
+}
+
+impl Timeout {
+    #[track_caller]
+    pub fn new(duration: Duration, child: impl Into<Composite>) -> Self {
+        Self {
+            duration,
+            child: child.into(),
+            task: ChildTask::default(),
+            deadline: None,
+        }
+    }
+}
+
+impl Future for Timeout {
+    type Output = RunStatus;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
+        if self.deadline.is_none() {
+            let clock = clock::current();
+            let remaining = persist::restore_remaining().unwrap_or(self.duration);
+            let deadline = clock.now() + remaining;
+            persist::save_deadline(&clock, deadline);
+            self.deadline = Some(clock::Timer::start(clock, deadline, cx.waker()));
+        }
+        let this = &mut *self;
+        let expired = this.deadline.as_ref().unwrap().expired();
+        // when ticked rarely (far agents,...) deadline can pass between two polls,
+        // it win over a child which would finish late
+        if expired && this.task.is_running() {
+            #[cfg(feature = "tracing")]
+            tracing::debug!(child = %this.child.label(), "timeout");
+            this.task.abort();
+            return Poll::Ready(RunStatus::Failure);
+        }
+        this.task.start(&this.child, Some(0));
+        if let Poll::Ready(status) = this.task.poll(cx) {
+            // child finished first, no need to be woken at deadline
+            this.deadline = None;
+            return Poll::Ready(status);
+        }
+
+        if expired {
+            #[cfg(feature = "tracing")]
+            tracing::debug!(child = %this.child.label(), "timeout");
+            this.task.abort();
+            return Poll::Ready(RunStatus::Failure);
+        }
+        Poll::Pending
+    }
+}
+
+impl Reset for Timeout {
+    fn reset(&mut self) {
+        self.task.abort();
+        self.deadline = None;
     }
 }
 
-IMPLEMENT_INTO_COMPOSITE!(UntilFailure);
+IMPLEMENT_INTO_COMPOSITE!(Timeout, reusable);
@@ -1,5 +1,5 @@
 use crate::RunStatus;
-use std::{future::Future, pin::Pin, rc::Rc};
+use std::{future::Future, panic::Location, pin::Pin, rc::Rc, task::Poll};
 
 /// Can create from Box::pin(an future)
 pub type BoxAction = Pin<Box<dyn Future<Output = RunStatus>>>;
@@ -7,11 +7,43 @@ pub type BoxAction = Pin<Box<dyn Future<Output = RunStatus>>>;
 #[derive(Clone)]
 pub struct Composite {
     pub name: String,
+    /// Type of node: "Action" for leaf, type name for built-in nodes
+    pub kind: &'static str,
+    /// Task can run again after it finished (see `Reset`)
+    pub reusable: bool,
+    /// Where node was created: call of `Composite::new`, `.into()` or macro
+    pub location: &'static Location<'static>,
+    /// Variable node was passed as to a macro, e.g. `patrol` in `Sequence! { patrol }`
+    pub binding: Option<&'static str>,
+    /// Free labels for viewers and tools, `#[tags("combat")]` in macros
+    pub tags: &'static [&'static str],
+    pub description: Option<&'static str>,
     // Box not allow clone
     // Rc will hold data and share it for clone
     pub task_production: Rc<dyn Fn() -> BoxAction>,
 }
 
+/// Display of `Composite::label`
+#[derive(Clone, Copy)]
+pub struct Label<'a>(&'a Composite);
+
+impl std::fmt::Display for Label<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let composite = self.0;
+        match composite
+            .binding
+            .filter(|binding| *binding != composite.name)
+        {
+            Some(binding) => write!(
+                f,
+                "{} [{}] at {}",
+                composite.name, binding, composite.location
+            ),
+            None => write!(f, "{} at {}", composite.name, composite.location),
+        }
+    }
+}
+
 /// Require:
 /// - Clone
 /// - Became future -> RunStatus
@@ -19,29 +51,217 @@ pub struct Composite {
 macro_rules! IMPLEMENT_INTO_COMPOSITE {
     ($type:ty) => {
         impl From<$type> for Composite {
+            #[track_caller]
             fn from(value: $type) -> Self {
                 $crate::composite::Composite::new(stringify!($type), move || {
                     let value_go = value.clone();
                     Box::pin(value_go)
                 })
+                .with_kind(stringify!($type))
+            }
+        }
+    };
+    // task reset itself when it finish, parent keep it for next run
+    ($type:ty, reusable) => {
+        impl From<$type> for Composite {
+            #[track_caller]
+            fn from(value: $type) -> Self {
+                $crate::composite::Composite::new(stringify!($type), move || {
+                    let value_go = value.clone();
+                    Box::pin($crate::composite::Reusable(value_go))
+                })
+                .with_kind(stringify!($type))
+                .with_reusable(true)
             }
         }
     };
 }
 
 impl Composite {
+    #[track_caller]
     pub fn new(name: impl Into<String>, task_production: impl Fn() -> BoxAction + 'static) -> Self {
         let name = name.into();
         Self {
             name,
+            kind: "Action",
+            reusable: false,
+            location: Location::caller(),
+            binding: None,
+            tags: &[],
+            description: None,
             task_production: Rc::new(task_production),
         }
     }
 
+    #[track_caller]
     pub fn new_action(task_production: impl Fn() -> BoxAction + 'static) -> Self {
-        Self {
-            name: "Action".into(),
-            task_production: Rc::new(task_production),
+        Self::new("Action", task_production)
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    pub fn with_binding(mut self, binding: &'static str) -> Self {
+        self.binding = Some(binding);
+        self
+    }
+
+    pub fn with_tags(mut self, tags: &'static [&'static str]) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    pub fn with_description(mut self, description: &'static str) -> Self {
+        self.description = Some(description);
+        self
+    }
+
+    pub fn with_kind(mut self, kind: &'static str) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Mark task as able to run again after finishing,
+    /// only true for task wrapped in `Reusable`
+    pub fn with_reusable(mut self, reusable: bool) -> Self {
+        self.reusable = reusable;
+        self
+    }
+
+    /// Name with binding and location, for logs and errors:
+    /// `Action [patrol] at src/main.rs:12:18`.
+    /// Formatted where it is displayed, logging a node does not allocate.
+    pub fn label(&self) -> Label<'_> {
+        Label(self)
+    }
+
+    /// Create new task of this composite.
+    /// Nodes start their children with it, so opt-in instrumentation
+    /// (Profiler, tracing,...) can watch them. Same as calling task_production otherwise.
+    pub fn start(&self) -> BoxAction {
+        crate::instrument::observe(self, None)
+    }
+
+    /// Same as start, for child at index of its parent
+    pub(crate) fn start_child(&self, index: usize) -> BoxAction {
+        crate::instrument::observe(self, Some(index))
+    }
+}
+
+/// Node able to run again after it finished.
+/// Reset state of run (running child index,...) but keep tasks of children,
+/// so next run does not allocate them again.
+pub trait Reset {
+    fn reset(&mut self);
+}
+
+/// Custom node made with `#[derive(Behavior)]`
+pub trait Behavior {
+    /// Name of its Composite
+    const NAME: &'static str;
+
+    /// Children in order, for viewers and debugging
+    fn children(&self) -> Vec<&Composite>;
+}
+
+/// Task reset when it finish, see `IMPLEMENT_INTO_COMPOSITE!(Type, reusable)`
+#[doc(hidden)]
+pub struct Reusable<T>(pub T);
+
+impl<T> Future for Reusable<T>
+where
+    T: Future<Output = RunStatus> + Reset + Unpin,
+{
+    type Output = RunStatus;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
+        let poll = Pin::new(&mut self.0).poll(cx);
+        if poll.is_ready() {
+            self.0.reset();
+        }
+        poll
+    }
+}
+
+/// Task of a `#[derive(Behavior)]` node, reset when it finish.
+/// Node can be !Unpin (state holding a timer future,...):
+/// it stay pinned for all its runs and is reset in place.
+#[doc(hidden)]
+pub struct PinnedReusable<T>(T);
+
+impl<T> PinnedReusable<T> {
+    /// # Safety
+    /// `T::reset` must only drop and overwrite fields in place, never move them out,
+    /// as the derived Reset does.
+    pub unsafe fn new(node: T) -> Self {
+        Self(node)
+    }
+}
+
+impl<T> Future for PinnedReusable<T>
+where
+    T: Future<Output = RunStatus> + Reset,
+{
+    type Output = RunStatus;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
+        // node is structurally pinned, and reset keep it in place (see `new`)
+        let mut node = unsafe { self.map_unchecked_mut(|this| &mut this.0) };
+        let poll = node.as_mut().poll(cx);
+        if poll.is_ready() {
+            unsafe { node.get_unchecked_mut().reset() };
+        }
+        poll
+    }
+}
+
+/// Task of a child kept by its parent.
+/// After it finish, task of reusable child is kept and polled again
+/// on next start, so running same child again does not allocate.
+#[derive(Default)]
+pub(crate) struct ChildTask {
+    task: Option<BoxAction>,
+    reusable: bool,
+    running: bool,
+}
+
+impl ChildTask {
+    pub(crate) fn is_running(&self) -> bool {
+        self.running
+    }
+
+    /// Start task of child, no-op if already running
+    pub(crate) fn start(&mut self, child: &Composite, index: Option<usize>) {
+        if self.running {
+            return;
+        }
+        if !self.reusable || self.task.is_none() {
+            // observed task is wrapped for one run only
+            self.reusable = child.reusable && !crate::instrument::is_observing();
+            self.task = Some(crate::instrument::observe(child, index));
+        }
+        self.running = true;
+    }
+
+    pub(crate) fn poll(&mut self, cx: &mut std::task::Context<'_>) -> Poll<RunStatus> {
+        let task = self.task.as_mut().expect("child task is started");
+        let poll = task.as_mut().poll(cx);
+        if poll.is_ready() {
+            self.running = false;
+            if !self.reusable {
+                self.task = None;
+            }
+        }
+        poll
+    }
+
+    /// Drop running task (interrupted, timeout,...)
+    pub(crate) fn abort(&mut self) {
+        if self.running {
+            self.running = false;
+            self.task = None;
         }
     }
 }
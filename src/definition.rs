@@ -0,0 +1,175 @@
+use std::{future::Future, pin::Pin, rc::Rc, task::Poll};
+
+use crate::{
+    composite::{ChildTask, Composite},
+    RunStatus,
+};
+
+/// Immutable tree built once and shared by every agent.
+/// Nodes keep their children behind Rc, so an instance only allocate
+/// state of nodes being run (running child index, task of child,...).
+///
+/// let guard = TreeDefinition::new(build_guard_tree());
+/// let agents: Vec<_> = (0..10_000).map(|_| guard.instantiate()).collect();
+#[derive(Clone)]
+pub struct TreeDefinition {
+    root: Rc<Composite>,
+}
+
+impl TreeDefinition {
+    #[track_caller]
+    pub fn new(root: impl Into<Composite>) -> Self {
+        Self {
+            root: Rc::new(root.into()),
+        }
+    }
+
+    pub fn root(&self) -> &Composite {
+        &self.root
+    }
+
+    /// New idle instance, nothing is allocated until it is polled
+    pub fn instantiate(&self) -> TreeInstance {
+        TreeInstance {
+            definition: self.clone(),
+            task: ChildTask::default(),
+            status: None,
+        }
+    }
+
+    /// Number of definition handles alive (instances included)
+    pub fn shared_count(&self) -> usize {
+        Rc::strong_count(&self.root)
+    }
+}
+
+impl From<Composite> for TreeDefinition {
+    fn from(root: Composite) -> Self {
+        Self::new(root)
+    }
+}
+
+/// Runtime state of one agent running a shared definition.
+/// Task is created at first poll, polling again after it finish start a new run.
+/// Task of built-in root is kept and reset between runs, so running tree
+/// again and again (once per frame,...) does not allocate its nodes again.
+pub struct TreeInstance {
+    definition: TreeDefinition,
+    task: ChildTask,
+    status: Option<RunStatus>,
+}
+
+impl TreeInstance {
+    pub fn definition(&self) -> &TreeDefinition {
+        &self.definition
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.task.is_running()
+    }
+
+    /// Status of last finished run
+    pub fn last_status(&self) -> Option<RunStatus> {
+        self.status
+    }
+
+    /// Drop running task, next poll start from root
+    pub fn reset(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl Future for TreeInstance {
+    type Output = RunStatus;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        this.task.start(&this.definition.root, None);
+        let Poll::Ready(status) = this.task.poll(cx) else {
+            return Poll::Pending;
+        };
+        this.status = Some(status);
+        Poll::Ready(status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::Cell,
+        task::{Context, Waker},
+    };
+
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn instances_share_definition() {
+        let gate = Rc::new(Cell::new(false));
+        let definition = TreeDefinition::new(Sequence::new([
+            Composite::new("wait_gate", {
+                let gate = gate.clone();
+                move || {
+                    let gate = gate.clone();
+                    Box::pin(std::future::poll_fn(move |_| match gate.get() {
+                        true => Poll::Ready(RunStatus::Success),
+                        false => Poll::Pending,
+                    }))
+                }
+            }),
+            Composite::new("done", || Box::pin(async { RunStatus::Success })),
+        ]));
+
+        let mut agents: Vec<_> = (0..1000).map(|_| definition.instantiate()).collect();
+        assert_eq!(definition.shared_count(), 1001);
+        assert!(agents.iter().all(|agent| !agent.is_running()));
+
+        let mut cx = Context::from_waker(Waker::noop());
+        for agent in &mut agents[..10] {
+            assert!(Pin::new(agent).poll(&mut cx).is_pending());
+        }
+        assert_eq!(agents.iter().filter(|agent| agent.is_running()).count(), 10);
+
+        gate.set(true);
+        let first = &mut agents[0];
+        assert_eq!(
+            Pin::new(&mut *first).poll(&mut cx),
+            Poll::Ready(RunStatus::Success)
+        );
+        assert!(!first.is_running());
+        assert_eq!(first.last_status(), Some(RunStatus::Success));
+
+        // other instances keep their own progress
+        agents[1].reset();
+        assert!(!agents[1].is_running());
+        assert!(agents[2].is_running());
+    }
+
+    #[test]
+    fn instance_run_again_after_finish() {
+        let runs = Rc::new(Cell::new(0));
+        let definition = TreeDefinition::new(Sequence::new([
+            Inverter::new(Inverter::new(Composite::new("count", {
+                let runs = runs.clone();
+                move || {
+                    runs.set(runs.get() + 1);
+                    Box::pin(async { RunStatus::Success })
+                }
+            })))
+            .into(),
+            Wait::new(std::time::Duration::ZERO).into(),
+        ]));
+        let mut agent = definition.instantiate();
+        let mut cx = Context::from_waker(Waker::noop());
+        for run in 1..=3 {
+            // built-in nodes reset themselves, run start from first child again
+            let status = loop {
+                if let Poll::Ready(status) = Pin::new(&mut agent).poll(&mut cx) {
+                    break status;
+                }
+            };
+            assert_eq!(status, RunStatus::Success);
+            assert_eq!(runs.get(), run);
+        }
+    }
+}
@@ -0,0 +1,429 @@
+use std::{
+    cell::RefCell,
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    task::Poll,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    composite::{BoxAction, Composite},
+    RunStatus,
+};
+
+/// Node being observed
+pub(crate) struct NodeInfo {
+    pub name: String,
+    /// "Action" for leaf, type name for built-in nodes
+    pub kind: &'static str,
+    /// Names from root to this node joined by ';'
+    pub path: Rc<str>,
+    /// Child indexes from root to this node joined by '.', root is "0"
+    /// Stable as long as tree shape does not change
+    pub address: Rc<str>,
+}
+
+pub(crate) enum NodeEvent {
+    /// First poll of a run
+    Started,
+    Polled {
+        /// Time inside poll
+        busy: Duration,
+        /// Time inside poll, without time spent in children poll
+        self_busy: Duration,
+        pending: bool,
+    },
+    Finished {
+        status: RunStatus,
+        /// First poll to completion
+        total: Duration,
+        /// Sum of busy time of this run
+        busy: Duration,
+    },
+    /// Dropped after first poll, before finishing
+    Aborted,
+}
+
+pub(crate) trait Observer {
+    fn on_event(&self, node: &NodeInfo, event: NodeEvent);
+
+    /// Status to finish node with, instead of running it
+    /// (journal replay completed leaves with it)
+    fn replay(&self, _node: &NodeInfo) -> Option<RunStatus> {
+        None
+    }
+}
+
+type Observers = Rc<[Rc<dyn Observer>]>;
+
+struct Frame {
+    path: Rc<str>,
+    address: Rc<str>,
+    children_busy: Duration,
+}
+
+thread_local! {
+    /// Observers of the scope being polled
+    static OBSERVERS: RefCell<Option<Observers>> = const { RefCell::new(None) };
+    /// Observed nodes currently inside their poll, last one is deepest
+    static STACK: RefCell<Vec<Frame>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Hook between a node and the child it start.
+/// When nothing is observing, task is created as is.
+/// Inside an observed scope (profiler,...) every started child is wrapped
+/// so observers get notified about its polls and its result.
+/// An observer can also replay a result, then task is never created.
+/// With `tracing` feature, every task is also run inside its own span.
+pub(crate) fn observe(composite: &Composite, index: Option<usize>) -> BoxAction {
+    let Some(observers) = OBSERVERS.with(|o| o.borrow().clone()) else {
+        return traced(composite, index, (composite.task_production)());
+    };
+    let (path, address) = STACK.with(|stack| match stack.borrow().last() {
+        Some(parent) => (
+            format!("{};{}", parent.path, composite.name).into(),
+            format!("{}.{}", parent.address, index.unwrap_or_default()).into(),
+        ),
+        None => (composite.name.as_str().into(), "0".into()),
+    });
+    let node = NodeInfo {
+        name: composite.name.clone(),
+        kind: composite.kind,
+        path,
+        address,
+    };
+    let fut: BoxAction = match observers.iter().find_map(|o| o.replay(&node)) {
+        Some(status) => Box::pin(std::future::ready(status)),
+        None => (composite.task_production)(),
+    };
+    Box::pin(Observed {
+        node,
+        observers,
+        fut: traced(composite, index, fut),
+        first_poll: None,
+        busy: Duration::ZERO,
+        finished: false,
+    })
+}
+
+/// Task started now would be wrapped (observer, tracing span),
+/// so it can not be reused for another run
+pub(crate) fn is_observing() -> bool {
+    #[cfg(feature = "tracing")]
+    if tracing::level_enabled!(tracing::Level::INFO) {
+        return true;
+    }
+    OBSERVERS.with(|o| o.borrow().is_some())
+}
+
+/// Address of observed node being polled
+pub(crate) fn current_address() -> Option<Rc<str>> {
+    STACK.with(|stack| stack.borrow().last().map(|frame| frame.address.clone()))
+}
+
+/// Run root inside a scope watched by observer
+/// Scope can be nested, inner scope is watched by both
+pub(crate) fn scope(observer: Rc<dyn Observer>, root: &Composite) -> BoxAction {
+    Box::pin(Scope {
+        observer,
+        root: root.clone(),
+        fut: None,
+    })
+}
+
+struct Scope {
+    observer: Rc<dyn Observer>,
+    root: Composite,
+    fut: Option<BoxAction>,
+}
+
+/// Restore observers of outer scope even if poll panic
+struct ScopeGuard(Option<Observers>);
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        let outer = self.0.take();
+        OBSERVERS.with(|o| *o.borrow_mut() = outer);
+    }
+}
+
+impl Future for Scope {
+    type Output = RunStatus;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
+        let outer = OBSERVERS.with(|o| o.borrow().clone());
+        let mut observers: Vec<_> = outer.iter().flat_map(|o| o.iter().cloned()).collect();
+        observers.push(self.observer.clone());
+        OBSERVERS.with(|o| *o.borrow_mut() = Some(observers.into()));
+        let _guard = ScopeGuard(outer);
+
+        if self.fut.is_none() {
+            let fut = self.root.start();
+            self.fut = Some(fut);
+        }
+        self.fut.as_mut().unwrap().as_mut().poll(cx)
+    }
+}
+
+struct Observed {
+    node: NodeInfo,
+    observers: Observers,
+    fut: BoxAction,
+    first_poll: Option<Instant>,
+    busy: Duration,
+    finished: bool,
+}
+
+impl Drop for Observed {
+    fn drop(&mut self) {
+        if self.first_poll.is_some() && !self.finished {
+            self.notify(|| NodeEvent::Aborted);
+        }
+    }
+}
+
+/// Pop frame even if poll panic
+struct FrameGuard;
+
+impl Drop for FrameGuard {
+    fn drop(&mut self) {
+        STACK.with(|stack| stack.borrow_mut().pop());
+    }
+}
+
+impl Observed {
+    fn notify(&self, event: impl Fn() -> NodeEvent) {
+        for observer in self.observers.iter() {
+            observer.on_event(&self.node, event());
+        }
+    }
+}
+
+impl Future for Observed {
+    type Output = RunStatus;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let begin = Instant::now();
+        if this.first_poll.is_none() {
+            this.first_poll = Some(begin);
+            this.notify(|| NodeEvent::Started);
+        }
+
+        STACK.with(|stack| {
+            stack.borrow_mut().push(Frame {
+                path: this.node.path.clone(),
+                address: this.node.address.clone(),
+                children_busy: Duration::ZERO,
+            })
+        });
+        let guard = FrameGuard;
+        let poll = this.fut.as_mut().poll(cx);
+        let busy = begin.elapsed();
+        let children_busy = STACK.with(|stack| {
+            stack
+                .borrow()
+                .last()
+                .map(|frame| frame.children_busy)
+                .unwrap_or_default()
+        });
+        drop(guard);
+        STACK.with(|stack| {
+            if let Some(parent) = stack.borrow_mut().last_mut() {
+                parent.children_busy += busy;
+            }
+        });
+
+        this.busy += busy;
+        this.notify(|| NodeEvent::Polled {
+            busy,
+            self_busy: busy.saturating_sub(children_busy),
+            pending: poll.is_pending(),
+        });
+        if let Poll::Ready(status) = poll {
+            this.finished = true;
+            let total = this.first_poll.map(|t| t.elapsed()).unwrap_or_default();
+            let busy = this.busy;
+            this.notify(|| NodeEvent::Finished {
+                status,
+                total,
+                busy,
+            });
+        }
+        poll
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+fn traced(_composite: &Composite, _index: Option<usize>, fut: BoxAction) -> BoxAction {
+    fut
+}
+
+/// Span for one run of node, it is created inside poll of parent
+/// so it become child of parent span.
+/// Span name must be static, node name is set to `otel.name`
+/// which is used as span name by OpenTelemetry/Jaeger exporters.
+#[cfg(feature = "tracing")]
+fn traced(composite: &Composite, index: Option<usize>, fut: BoxAction) -> BoxAction {
+    let span = tracing::info_span!(
+        "bhv_node",
+        otel.name = %composite.name,
+        name = %composite.name,
+        kind = composite.kind,
+        binding = composite.binding,
+        location = %composite.location,
+        child_index = index,
+        status = tracing::field::Empty,
+    );
+    if span.is_disabled() {
+        return fut;
+    }
+    Box::pin(Traced { span, fut })
+}
+
+#[cfg(feature = "tracing")]
+struct Traced {
+    span: tracing::Span,
+    fut: BoxAction,
+}
+
+#[cfg(feature = "tracing")]
+impl Future for Traced {
+    type Output = RunStatus;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let _enter = this.span.enter();
+        let poll = this.fut.as_mut().poll(cx);
+        if let Poll::Ready(status) = poll {
+            this.span.record("status", tracing::field::debug(status));
+        }
+        poll
+    }
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use std::sync::Mutex;
+
+    use tracing::{field::Visit, span, Subscriber};
+
+    use crate::prelude::*;
+
+    #[derive(Debug, Default)]
+    struct SpanRecord {
+        name: String,
+        kind: String,
+        child_index: Option<u64>,
+        status: Option<String>,
+        parent: Option<u64>,
+    }
+
+    impl Visit for SpanRecord {
+        fn record_u64(&mut self, field: &tracing::field::Field, value: u64) {
+            if field.name() == "child_index" {
+                self.child_index = Some(value);
+            }
+        }
+
+        fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+            if field.name() == "kind" {
+                self.kind = value.into();
+            }
+        }
+
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            match field.name() {
+                "name" => self.name = format!("{value:?}"),
+                "status" => self.status = Some(format!("{value:?}")),
+                _ => {}
+            }
+        }
+    }
+
+    /// Keep every span with its parent
+    #[derive(Default)]
+    struct Collector {
+        spans: Mutex<Vec<SpanRecord>>,
+        entered: Mutex<Vec<u64>>,
+    }
+
+    impl Subscriber for &'static Collector {
+        fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, attrs: &span::Attributes<'_>) -> span::Id {
+            let mut record = SpanRecord {
+                parent: self.entered.lock().unwrap().last().copied(),
+                ..Default::default()
+            };
+            attrs.record(&mut record);
+            let mut spans = self.spans.lock().unwrap();
+            spans.push(record);
+            span::Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, span: &span::Id, values: &span::Record<'_>) {
+            let mut spans = self.spans.lock().unwrap();
+            values.record(&mut spans[span.into_u64() as usize - 1]);
+        }
+
+        fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+        fn event(&self, _: &tracing::Event<'_>) {}
+
+        fn enter(&self, span: &span::Id) {
+            self.entered.lock().unwrap().push(span.into_u64());
+        }
+
+        fn exit(&self, _: &span::Id) {
+            self.entered.lock().unwrap().pop();
+        }
+    }
+
+    #[test]
+    fn spans_mirror_tree() {
+        let collector: &'static Collector = Box::leak(Box::default());
+        let tree: Composite = Sequence::new([
+            Composite::new("first", || Box::pin(async { RunStatus::Success })),
+            Inverter::new(Composite::new("second", || {
+                Box::pin(async { RunStatus::Failure })
+            }))
+            .into(),
+        ])
+        .into();
+
+        let status = tracing::subscriber::with_default(collector, || {
+            let rt = tokio::runtime::Builder::new_current_thread()
+                .build()
+                .unwrap();
+            rt.block_on(tree.start())
+        });
+        assert_eq!(status, RunStatus::Success);
+
+        let spans = collector.spans.lock().unwrap();
+        let summary: Vec<_> = spans
+            .iter()
+            .map(|s| {
+                (
+                    s.name.as_str(),
+                    s.kind.as_str(),
+                    s.child_index,
+                    s.status.as_deref(),
+                    s.parent,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("Sequence", "Sequence", None, Some("Success"), None),
+                ("first", "Action", Some(0), Some("Success"), Some(1)),
+                ("Inverter", "Inverter", Some(1), Some("Success"), Some(1)),
+                ("second", "Action", Some(0), Some("Failure"), Some(3)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,421 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashSet, VecDeque},
+    fs::{File, OpenOptions},
+    io::{self, Read, Write},
+    path::PathBuf,
+    rc::Rc,
+};
+
+use crate::{
+    composite::{BoxAction, Composite},
+    instrument::{self, NodeEvent, NodeInfo, Observer},
+    RunStatus,
+};
+
+/// Completion of one leaf
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JournalEntry {
+    /// Child indexes from root joined by '.', root is "0"
+    pub address: String,
+    /// Name of leaf, replay stop when it does not match anymore
+    pub name: String,
+    pub status: RunStatus,
+}
+
+/// Append-only storage of journal entries
+pub trait JournalStore {
+    /// Every entry appended so far, in order
+    fn load(&mut self) -> io::Result<Vec<JournalEntry>>;
+
+    fn append(&mut self, entry: &JournalEntry) -> io::Result<()>;
+}
+
+/// Store kept in memory, clone share same entries
+#[derive(Debug, Default, Clone)]
+pub struct MemoryStore(Rc<RefCell<Vec<JournalEntry>>>);
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn entries(&self) -> Vec<JournalEntry> {
+        self.0.borrow().clone()
+    }
+}
+
+impl JournalStore for MemoryStore {
+    fn load(&mut self) -> io::Result<Vec<JournalEntry>> {
+        Ok(self.entries())
+    }
+
+    fn append(&mut self, entry: &JournalEntry) -> io::Result<()> {
+        self.0.borrow_mut().push(entry.clone());
+        Ok(())
+    }
+}
+
+/// Store in a local file, one `address<TAB>status<TAB>name` line per entry,
+/// with `\\`, `\n` and `\r` of name escaped.
+/// Each entry is synced to disk before next node start.
+/// A last line left incomplete or garbled by a crash is dropped when loading.
+#[derive(Debug)]
+pub struct FileStore {
+    path: PathBuf,
+    file: Option<File>,
+}
+
+impl FileStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            file: None,
+        }
+    }
+}
+
+fn invalid(line: &[u8]) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid journal line: {:?}", String::from_utf8_lossy(line)),
+    )
+}
+
+fn escape(name: &str) -> String {
+    let mut escaped = String::with_capacity(name.len());
+    for c in name.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(escaped: &str) -> Option<String> {
+    let mut name = String::with_capacity(escaped.len());
+    let mut chars = escaped.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            name.push(c);
+            continue;
+        }
+        match chars.next()? {
+            '\\' => name.push('\\'),
+            'n' => name.push('\n'),
+            'r' => name.push('\r'),
+            _ => return None,
+        }
+    }
+    Some(name)
+}
+
+/// Entry of one line with its '\n', None for empty line
+fn parse_line(line: &[u8]) -> io::Result<Option<JournalEntry>> {
+    let text = line
+        .strip_suffix(b"\n")
+        .and_then(|text| std::str::from_utf8(text).ok())
+        .ok_or_else(|| invalid(line))?;
+    if text.is_empty() {
+        return Ok(None);
+    }
+    let mut fields = text.splitn(3, '\t');
+    let (Some(address), Some(status), Some(name)) = (fields.next(), fields.next(), fields.next())
+    else {
+        return Err(invalid(line));
+    };
+    let status = match status {
+        "Success" => RunStatus::Success,
+        "Failure" => RunStatus::Failure,
+        _ => return Err(invalid(line)),
+    };
+    let name = unescape(name).ok_or_else(|| invalid(line))?;
+    Ok(Some(JournalEntry {
+        address: address.into(),
+        name,
+        status,
+    }))
+}
+
+impl JournalStore for FileStore {
+    fn load(&mut self) -> io::Result<Vec<JournalEntry>> {
+        let mut file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+
+        let mut entries = Vec::new();
+        // end of last valid line
+        let mut valid_len = 0;
+        let mut lines = bytes.split_inclusive(|byte| *byte == b'\n').peekable();
+        while let Some(line) = lines.next() {
+            match parse_line(line) {
+                Ok(entry) => entries.extend(entry),
+                // torn by a crash while appending
+                Err(_) if lines.peek().is_none() => break,
+                Err(err) => return Err(err),
+            }
+            valid_len += line.len();
+        }
+
+        if valid_len < bytes.len() {
+            // next entries must not be appended to the torn line
+            let file = OpenOptions::new().write(true).open(&self.path)?;
+            file.set_len(valid_len as u64)?;
+            file.sync_data()?;
+        }
+        Ok(entries)
+    }
+
+    fn append(&mut self, entry: &JournalEntry) -> io::Result<()> {
+        if self.file.is_none() {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+            self.file = Some(file);
+        }
+        let file = self.file.as_mut().unwrap();
+        // name last, so it can contain tabs
+        let name = escape(&entry.name);
+        writeln!(file, "{}\t{:?}\t{}", entry.address, entry.status, name)?;
+        file.sync_data()
+    }
+}
+
+struct JournalState {
+    store: RefCell<Box<dyn JournalStore>>,
+    /// Recorded statuses not replayed yet, per address.
+    /// A leaf run again (UntilSuccess,...) replay them in order.
+    pending: RefCell<BTreeMap<String, VecDeque<JournalEntry>>>,
+    /// Replaying stop at first leaf without matching entry
+    replaying: RefCell<bool>,
+    /// Leaves finishing with a replayed status, not appended again
+    replayed: RefCell<HashSet<Rc<str>>>,
+    error: RefCell<Option<io::Error>>,
+}
+
+/// Durable journal of leaf completions for workflow-like trees,
+/// where running a finished side-effecting leaf again is harmful.
+/// Every leaf (kind "Action") finishing is appended to store.
+/// When tree is run again with same journal, finished leaves are skipped
+/// with their recorded status, until first leaf without record:
+/// execution continues from there.
+///
+/// Tree shape must be same between runs, random nodes need a seed.
+///
+/// let journal = Journal::open("job.journal")?;
+/// let status = journal.run(&tree).await;
+/// if let Some(err) = journal.take_error() { ... }
+#[derive(Clone)]
+pub struct Journal {
+    state: Rc<JournalState>,
+}
+
+impl Journal {
+    /// Load recorded entries from store
+    pub fn new(mut store: impl JournalStore + 'static) -> io::Result<Self> {
+        let mut pending: BTreeMap<String, VecDeque<JournalEntry>> = BTreeMap::new();
+        for entry in store.load()? {
+            pending
+                .entry(entry.address.clone())
+                .or_default()
+                .push_back(entry);
+        }
+        Ok(Self {
+            state: Rc::new(JournalState {
+                store: RefCell::new(Box::new(store)),
+                pending: RefCell::new(pending),
+                replaying: RefCell::new(true),
+                replayed: RefCell::default(),
+                error: RefCell::default(),
+            }),
+        })
+    }
+
+    /// Journal stored in local file, created at first entry
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        Self::new(FileStore::new(path))
+    }
+
+    /// Create task of root, its leaves are journaled
+    pub fn run(&self, root: &Composite) -> BoxAction {
+        instrument::scope(Rc::new(self.clone()), root)
+    }
+
+    /// Still skipping recorded leaves
+    pub fn is_replaying(&self) -> bool {
+        *self.state.replaying.borrow()
+    }
+
+    /// First error of store while appending, tree keep running without it
+    pub fn take_error(&self) -> Option<io::Error> {
+        self.state.error.borrow_mut().take()
+    }
+}
+
+impl Observer for Journal {
+    fn replay(&self, node: &NodeInfo) -> Option<RunStatus> {
+        if node.kind != "Action" || !self.is_replaying() {
+            return None;
+        }
+        let mut pending = self.state.pending.borrow_mut();
+        let entry = pending
+            .get(&*node.address)
+            .and_then(|entries| entries.front())
+            .filter(|entry| entry.name == node.name);
+        let Some(status) = entry.map(|entry| entry.status) else {
+            *self.state.replaying.borrow_mut() = false;
+            return None;
+        };
+        pending.get_mut(&*node.address).unwrap().pop_front();
+        self.state
+            .replayed
+            .borrow_mut()
+            .insert(node.address.clone());
+        Some(status)
+    }
+
+    fn on_event(&self, node: &NodeInfo, event: NodeEvent) {
+        let NodeEvent::Finished { status, .. } = event else {
+            return;
+        };
+        if node.kind != "Action" || self.state.replayed.borrow_mut().remove(&node.address) {
+            return;
+        }
+        let entry = JournalEntry {
+            address: node.address.to_string(),
+            name: node.name.clone(),
+            status,
+        };
+        if let Err(err) = self.state.store.borrow_mut().append(&entry) {
+            self.state.error.borrow_mut().get_or_insert(err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    type Log = Rc<RefCell<Vec<&'static str>>>;
+
+    fn step(log: &Log, name: &'static str, status: RunStatus) -> Composite {
+        let log = log.clone();
+        Composite::new(name, move || {
+            log.borrow_mut().push(name);
+            Box::pin(async move { status })
+        })
+    }
+
+    #[tokio::test]
+    async fn replay_completed_leaves() {
+        let log = Log::default();
+        let crash = Rc::new(RefCell::new(true));
+        let tree: Composite = Sequence::new([
+            step(&log, "create", RunStatus::Success),
+            PrioritySelector::new([
+                step(&log, "fast_path", RunStatus::Failure),
+                step(&log, "slow_path", RunStatus::Success),
+            ])
+            .into(),
+            Composite::new("upload", {
+                let (log, crash) = (log.clone(), crash.clone());
+                move || {
+                    log.borrow_mut().push("upload");
+                    let crash = *crash.borrow();
+                    Box::pin(async move {
+                        if crash {
+                            // process die while uploading
+                            std::future::pending::<()>().await;
+                        }
+                        RunStatus::Success
+                    })
+                }
+            }),
+        ])
+        .into();
+
+        let store = MemoryStore::new();
+        let journal = Journal::new(store.clone()).unwrap();
+        let task = journal.run(&tree);
+        let _ = tokio::time::timeout(std::time::Duration::from_millis(10), task).await;
+        assert_eq!(store.entries().len(), 3);
+
+        // restart
+        log.borrow_mut().clear();
+        *crash.borrow_mut() = false;
+        let journal = Journal::new(store.clone()).unwrap();
+        assert_eq!(journal.run(&tree).await, RunStatus::Success);
+        assert_eq!(*log.borrow(), ["upload"]);
+        assert!(!journal.is_replaying());
+        assert_eq!(
+            store.entries().last(),
+            Some(&JournalEntry {
+                address: "0.2".into(),
+                name: "upload".into(),
+                status: RunStatus::Success,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn file_store_round_trip() {
+        let path = std::env::temp_dir().join(format!("bhv-journal-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let log = Log::default();
+        let tree: Composite = Sequence::new([
+            step(&log, "a", RunStatus::Success),
+            step(&log, "b\twith tab", RunStatus::Success),
+            step(&log, "c\nwith \\n", RunStatus::Success),
+        ])
+        .into();
+
+        let journal = Journal::open(&path).unwrap();
+        assert_eq!(journal.run(&tree).await, RunStatus::Success);
+        assert!(journal.take_error().is_none());
+
+        let entries = FileStore::new(&path).load().unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[1].name, "b\twith tab");
+        assert_eq!(entries[2].name, "c\nwith \\n");
+
+        log.borrow_mut().clear();
+        let journal = Journal::open(&path).unwrap();
+        assert_eq!(journal.run(&tree).await, RunStatus::Success);
+        assert!(log.borrow().is_empty());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn file_store_drop_torn_last_line() {
+        let path = std::env::temp_dir().join(format!("bhv-journal-torn-{}", std::process::id()));
+        let entry = |address: &str| JournalEntry {
+            address: address.into(),
+            name: "step".into(),
+            status: RunStatus::Success,
+        };
+        std::fs::write(&path, "0.0\tSuccess\tstep\n0.1\tSucc").unwrap();
+
+        let mut store = FileStore::new(&path);
+        assert_eq!(store.load().unwrap(), [entry("0.0")]);
+        store.append(&entry("0.1")).unwrap();
+        assert_eq!(
+            FileStore::new(&path).load().unwrap(),
+            [entry("0.0"), entry("0.1")]
+        );
+
+        // corruption before last line is not a crash
+        std::fs::write(&path, "0.0\tDone\tstep\n0.1\tSuccess\tstep\n").unwrap();
+        let err = FileStore::new(&path).load().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
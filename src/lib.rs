@@ -1,14 +1,40 @@
 #[macro_use]
 pub mod composite;
+pub mod blackboard;
+pub mod clock;
 pub mod common_behaviors;
+pub mod definition;
+mod instrument;
+pub mod journal;
+pub mod persist;
+pub mod profiler;
+pub mod scheduler;
+pub mod subtree;
+pub mod testing;
+#[macro_use]
+pub mod trace;
+pub mod typed;
+pub mod utility;
 #[cfg(feature = "macros")]
 pub mod macros {
     pub use bhv_async_macros::*;
 }
+#[cfg(feature = "macros")]
+pub use bhv_async_macros::behavior;
 /// Re-export all type in bhv-async
 pub mod prelude {
+    pub use crate::assert_trace;
+    pub use crate::blackboard::*;
     pub use crate::common_behaviors::*;
     pub use crate::composite::*;
+    pub use crate::definition::*;
+    pub use crate::journal::*;
+    pub use crate::persist::*;
+    pub use crate::profiler::*;
+    pub use crate::scheduler::*;
+    pub use crate::subtree::*;
+    pub use crate::trace::*;
+    pub use crate::utility::*;
     pub use crate::RunStatus;
 
     #[cfg(feature = "macros")]
@@ -25,6 +51,17 @@ pub enum RunStatus {
 #[cfg(test)]
 mod tests {
     pub use crate::prelude::*;
+    use std::{cell::RefCell, rc::Rc};
+
+    type Log = Rc<RefCell<Vec<&'static str>>>;
+
+    fn logged(log: &Log, name: &'static str, status: RunStatus) -> Composite {
+        let log = log.clone();
+        Composite::new(name, move || {
+            log.borrow_mut().push(name);
+            Box::pin(async move { status })
+        })
+    }
 
     #[tokio::test]
     pub async fn run_tree() {
@@ -46,42 +83,213 @@ mod tests {
             .into(),
         ])
         .await;
+        assert_eq!(seq, RunStatus::Success);
+    }
+
+    #[tokio::test]
+    pub async fn renamed_optional_child_still_fail_selector() {
+        let log = Log::default();
+        let optional: Composite =
+            DecoratorContinue::new(|| true, logged(&log, "optional", RunStatus::Success)).into();
+        let status = PrioritySelector::new([
+            optional.with_name("x"),
+            logged(&log, "fallback", RunStatus::Success),
+        ])
+        .await;
+        assert_eq!(status, RunStatus::Success);
+        assert_eq!(*log.borrow(), ["optional", "fallback"]);
+    }
+
+    #[tokio::test]
+    pub async fn random_nodes_are_deterministic_with_seed() {
+        let run_order = |seed| async move {
+            let log = Log::default();
+            let childs = ["a", "b", "c", "d"].map(|name| logged(&log, name, RunStatus::Success));
+            let status = RandomSequence::with_seed(childs, seed).await;
+            assert_eq!(status, RunStatus::Success);
+            let order = log.borrow().clone();
+            order
+        };
+        let first = run_order(7).await;
+        assert_eq!(first, run_order(7).await);
+        let mut sorted = first.clone();
+        sorted.sort();
+        assert_eq!(sorted, ["a", "b", "c", "d"]);
+
+        // selector stop at first success, all others fail
+        let log = Log::default();
+        let status = RandomSelector::with_seed(
+            [
+                logged(&log, "fail", RunStatus::Failure),
+                logged(&log, "ok", RunStatus::Success),
+            ],
+            7,
+        )
+        .await;
+        assert_eq!(status, RunStatus::Success);
+        assert_eq!(log.borrow().last(), Some(&"ok"));
+    }
+
+    #[tokio::test]
+    pub async fn weighted_selector_skip_invalid_weight() {
+        let log = Log::default();
+        let node: Composite = WeightedSelector::with_seed(
+            [
+                (0.0, logged(&log, "never", RunStatus::Failure)),
+                (f32::NAN, logged(&log, "never", RunStatus::Failure)),
+                (-3.0, logged(&log, "never", RunStatus::Failure)),
+                (f32::INFINITY, logged(&log, "never", RunStatus::Failure)),
+                (1.0, logged(&log, "rare", RunStatus::Success)),
+                (9.0, logged(&log, "often", RunStatus::Success)),
+            ],
+            42,
+        )
+        .into();
+        for _ in 0..100 {
+            assert_eq!((node.task_production)().await, RunStatus::Success);
+        }
+        {
+            let log = log.borrow();
+            assert_eq!(log.len(), 100);
+            assert!(!log.contains(&"never"));
+            let often = log.iter().filter(|name| **name == "often").count();
+            assert!(often > 50);
+        }
+
+        let empty = WeightedSelector::with_seed(
+            [
+                (0.0, logged(&Log::default(), "x", RunStatus::Success)),
+                (f32::NAN, logged(&Log::default(), "y", RunStatus::Success)),
+            ],
+            1,
+        );
+        assert_eq!(empty.await, RunStatus::Failure);
+    }
+
+    #[tokio::test]
+    pub async fn utility_selector_run_best_child() {
+        let log = Log::default();
+        let blackboard = Blackboard::new(0.2_f32);
+        let status = UtilitySelector::new([
+            UtilityChild::new(|| 0.5, logged(&log, "sync", RunStatus::Failure)),
+            UtilityChild::with_blackboard(
+                &blackboard,
+                |hp| 1.0 - hp,
+                logged(&log, "heal", RunStatus::Success),
+            ),
+            UtilityChild::new_async(
+                || Box::pin(async { 0.1 }),
+                logged(&log, "async", RunStatus::Failure),
+            ),
+        ])
+        .await;
+        assert_eq!(status, RunStatus::Success);
+        assert_eq!(*log.borrow(), ["heal"]);
+    }
+
+    #[tokio::test]
+    pub async fn utility_selector_switch_on_rescore() {
+        let log = Log::default();
+        let blackboard = Blackboard::new(1.0_f32);
+        let busy_log = log.clone();
+        let busy = Composite::new("busy", move || {
+            busy_log.borrow_mut().push("busy");
+            Box::pin(std::future::poll_fn(|cx| {
+                cx.waker().wake_by_ref();
+                std::task::Poll::Pending
+            }))
+        });
+        let selector = UtilitySelector::new([
+            UtilityChild::with_blackboard(&blackboard, |hp| *hp, busy),
+            UtilityChild::with_blackboard(
+                &blackboard,
+                |hp| 1.0 - hp,
+                logged(&log, "heal", RunStatus::Success),
+            ),
+        ])
+        .with_rescore(0.1);
+
+        let (status, _) = tokio::join!(selector, async {
+            tokio::task::yield_now().await;
+            blackboard.update(|hp| *hp = 0.0);
+        });
+        assert_eq!(status, RunStatus::Success);
+        assert_eq!(*log.borrow(), ["busy", "heal"]);
+    }
+
+    #[tokio::test]
+    pub async fn wait_and_timeout_with_system_clock() {
+        let begin = std::time::Instant::now();
+        let status = Sequence::new([
+            Wait::new(std::time::Duration::from_millis(20)).into(),
+            Timeout::new(
+                std::time::Duration::from_millis(20),
+                Wait::new(std::time::Duration::from_secs(60)),
+            )
+            .into(),
+        ])
+        .await;
+        assert_eq!(status, RunStatus::Failure);
+        assert!(begin.elapsed() >= std::time::Duration::from_millis(40));
+        assert!(begin.elapsed() < std::time::Duration::from_secs(10));
+    }
+
+    /// Same shape as examples/demo.rs without sleeps
+    #[tokio::test]
+    pub async fn demo_tree_trace() {
+        let leaf = |name: &'static str, status| {
+            Composite::new(name, move || Box::pin(async move { status }))
+        };
+        let tree: Composite = Sequence::new([
+            leaf("FirstChildinSequence", RunStatus::Success),
+            DecoratorContinue::new(|| false, leaf("Should not run 1", RunStatus::Failure)).into(),
+            Decorator::new(|| true, leaf("Should run 2", RunStatus::Success)).into(),
+            PrioritySelector::new([
+                leaf("Action", RunStatus::Failure),
+                DecoratorContinue::new(|| false, leaf("Should not run 3", RunStatus::Failure))
+                    .into(),
+                DecoratorContinue::new(|| true, leaf("Should run 5", RunStatus::Failure)).into(),
+                Decorator::new(|| true, leaf("Should run 4", RunStatus::Failure)).into(),
+            ])
+            .into(),
+        ])
+        .into();
+
+        let recorder = TraceRecorder::new();
+        assert_eq!(recorder.record(&tree).await, RunStatus::Failure);
+        assert!(!recorder.has_entered("Should not run 1"));
+        assert!(!recorder.has_entered("Should not run 3"));
+        assert_trace!(
+            recorder,
+            [
+                "Sequence",
+                "FirstChildinSequence",
+                "DecoratorContinue",
+                "Decorator",
+                "Should run 2",
+                "PrioritySelector",
+                "Action",
+                "DecoratorContinue",
+                "DecoratorContinue",
+                "Should run 5",
+                "Decorator",
+                "Should run 4",
+            ]
+        );
 
-        // Action! {
-        //     { wat ter face}
-        // };
-
-        // should be this ??
-        // tree! {
-        //     <Sequence>
-        //         <Action do={some_async_func}/>
-        //         <Action do={async {
-        //             // an block async
-        //         }}/>
-        //     </Sequence>
-        // }
-        //
-        // or like this
-        // Sequence! {
-        //     Action! {
-        //         something().await;
-        //     }
-        //     Action! {
-        //         something().await;
-        //     }
-        //     Action! {
-        //         something().await;
-        //     }
-        //     Decorator! {
-        //         conditionFunction,
-        //         child will be run,
-        //     }
-        //     Decorator! {
-        //         conditionFunction,
-        //         Sequence! {
-        //             so on
-        //         }
-        //     }
-        // }
+        recorder.clear();
+        let interrupted: Composite =
+            InterruptAction::new(|| false, Wait::new(std::time::Duration::from_secs(60))).into();
+        assert_eq!(recorder.record(&interrupted).await, RunStatus::Failure);
+        assert_trace!(
+            recorder,
+            [
+                enter "InterruptAction",
+                enter "Wait",
+                // running child is dropped when its parent finish
+                abort "Wait",
+                exit "InterruptAction" => Failure,
+            ]
+        );
     }
 }
@@ -0,0 +1,444 @@
+use std::{
+    cell::RefCell, collections::BTreeMap, future::Future, pin::Pin, rc::Rc, task::Poll,
+    time::Duration,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    blackboard::Blackboard,
+    clock::Clock,
+    composite::{BoxAction, Composite},
+    instrument::{self, NodeEvent, NodeInfo, Observer},
+    RunStatus,
+};
+
+/// Saved cursor of one node
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct NodeState {
+    /// Running child (Sequence, PrioritySelector, WeightedSelector,...)
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub index: Option<usize>,
+    /// Finished runs of a bounded repeat (UntilSuccess, UntilFailure)
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub runs: Option<usize>,
+    /// Children order of random nodes
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub order: Option<Vec<usize>>,
+    /// Time left before deadline (Wait, Timeout,...)
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub remaining: Option<Duration>,
+    /// Progress saved by a resumable leaf
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub progress: Option<String>,
+}
+
+/// Execution cursor of a tree, keyed by node address
+/// (child indexes from root joined by '.', root is "0")
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TreeSnapshot<B = ()> {
+    pub nodes: BTreeMap<String, NodeState>,
+    pub blackboard: B,
+}
+
+/// Cursor of node while it is running
+/// deadline is kept absolute and converted to remaining time at snapshot
+#[derive(Default)]
+struct LiveState {
+    index: Option<usize>,
+    runs: Option<usize>,
+    order: Option<Vec<usize>>,
+    deadline: Option<(Rc<dyn Clock>, Duration)>,
+    progress: Option<String>,
+}
+
+#[derive(Default)]
+struct Cursors {
+    live: RefCell<BTreeMap<Rc<str>, LiveState>>,
+    /// Restored states waiting for their node to start
+    restore: RefCell<BTreeMap<String, NodeState>>,
+}
+
+thread_local! {
+    /// Cursors of the tree being polled
+    static ACTIVE: RefCell<Option<Rc<Cursors>>> = const { RefCell::new(None) };
+}
+
+/// Save a part of current node state, no-op outside Checkpointer
+fn save(update: impl FnOnce(&mut LiveState)) {
+    let Some(cursors) = ACTIVE.with(|active| active.borrow().clone()) else {
+        return;
+    };
+    let Some(address) = instrument::current_address() else {
+        return;
+    };
+    update(cursors.live.borrow_mut().entry(address).or_default());
+}
+
+/// Take a part of restored state of current node
+fn restore<T>(take: impl FnOnce(&mut NodeState) -> Option<T>) -> Option<T> {
+    let cursors = ACTIVE.with(|active| active.borrow().clone())?;
+    let address = instrument::current_address()?;
+    let mut restore = cursors.restore.borrow_mut();
+    take(restore.get_mut(&*address)?)
+}
+
+pub(crate) fn save_index(index: usize) {
+    save(|state| state.index = Some(index));
+}
+
+pub(crate) fn restore_index() -> Option<usize> {
+    restore(|state| state.index.take())
+}
+
+pub(crate) fn save_runs(runs: usize) {
+    save(|state| state.runs = Some(runs));
+}
+
+pub(crate) fn restore_runs() -> Option<usize> {
+    restore(|state| state.runs.take())
+}
+
+pub(crate) fn save_order(order: &[usize]) {
+    save(|state| state.order = Some(order.to_vec()));
+}
+
+pub(crate) fn restore_order() -> Option<Vec<usize>> {
+    restore(|state| state.order.take())
+}
+
+pub(crate) fn save_deadline(clock: &Rc<dyn Clock>, deadline: Duration) {
+    save(|state| state.deadline = Some((clock.clone(), deadline)));
+}
+
+pub(crate) fn restore_remaining() -> Option<Duration> {
+    restore(|state| state.remaining.take())
+}
+
+/// Save progress of a resumable leaf, call it from inside the leaf task.
+/// It is given back to the leaf when tree is resumed from a snapshot.
+/// No-op when tree is not run by a Checkpointer.
+pub fn save_progress(progress: impl Into<String>) {
+    let progress = progress.into();
+    save(|state| state.progress = Some(progress));
+}
+
+/// Leaf able to continue from a saved progress instead of starting again
+///
+/// let download = Composite::resumable("download", |saved: Option<String>| -> BoxAction {
+///     Box::pin(async move {
+///         let mut offset: u64 = saved.and_then(|s| s.parse().ok()).unwrap_or(0);
+///         while offset < SIZE {
+///             offset += fetch_chunk(offset).await;
+///             save_progress(offset.to_string());
+///         }
+///         RunStatus::Success
+///     })
+/// });
+pub trait ResumableLeaf: 'static {
+    fn resume(&self, saved: Option<String>) -> BoxAction;
+}
+
+impl<F> ResumableLeaf for F
+where
+    F: Fn(Option<String>) -> BoxAction + 'static,
+{
+    fn resume(&self, saved: Option<String>) -> BoxAction {
+        self(saved)
+    }
+}
+
+impl Composite {
+    pub fn resumable(name: impl Into<String>, leaf: impl ResumableLeaf) -> Self {
+        let leaf = Rc::new(leaf);
+        Composite::new(name, move || {
+            let leaf = leaf.clone();
+            Box::pin(async move {
+                // restored state can only be read during poll
+                let saved = restore(|state| state.progress.take());
+                leaf.resume(saved).await
+            })
+        })
+    }
+}
+
+/// Keep execution cursor of a tree so it can be saved and resumed later
+/// (after a restart,...). Saved: running child of composites,
+/// runs done by bounded repeats, shuffled order of random nodes, time left of Wait/Timeout,
+/// progress of resumable leaves. Other leaves start again from scratch.
+///
+/// let checkpointer = Checkpointer::new();
+/// let task = checkpointer.run(&tree);
+/// ...
+/// let snapshot = checkpointer.snapshot_with(&blackboard);
+///
+/// // after restart, same tree shape
+/// let blackboard = Blackboard::new(snapshot.blackboard.clone());
+/// let task = Checkpointer::new().resume(&tree, &snapshot);
+#[derive(Default, Clone)]
+pub struct Checkpointer {
+    cursors: Rc<Cursors>,
+}
+
+impl Checkpointer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create task of root, its cursor is kept by this checkpointer
+    pub fn run(&self, root: &Composite) -> BoxAction {
+        Box::pin(Checkpointed {
+            cursors: self.cursors.clone(),
+            task: instrument::scope(Rc::new(self.clone()), root),
+        })
+    }
+
+    /// Same as run, but nodes continue from snapshot cursor
+    pub fn resume<B>(&self, root: &Composite, snapshot: &TreeSnapshot<B>) -> BoxAction {
+        *self.cursors.restore.borrow_mut() = snapshot.nodes.clone();
+        self.run(root)
+    }
+
+    pub fn snapshot(&self) -> TreeSnapshot {
+        let nodes = self
+            .cursors
+            .live
+            .borrow()
+            .iter()
+            .map(|(address, live)| {
+                let state = NodeState {
+                    index: live.index,
+                    runs: live.runs,
+                    order: live.order.clone(),
+                    remaining: live
+                        .deadline
+                        .as_ref()
+                        .map(|(clock, deadline)| deadline.saturating_sub(clock.now())),
+                    progress: live.progress.clone(),
+                };
+                (address.to_string(), state)
+            })
+            .collect();
+        TreeSnapshot {
+            nodes,
+            blackboard: (),
+        }
+    }
+
+    /// Snapshot with a copy of blackboard data
+    pub fn snapshot_with<B: Clone>(&self, blackboard: &Blackboard<B>) -> TreeSnapshot<B> {
+        let TreeSnapshot { nodes, .. } = self.snapshot();
+        TreeSnapshot {
+            nodes,
+            blackboard: blackboard.get().clone(),
+        }
+    }
+}
+
+impl Observer for Checkpointer {
+    fn on_event(&self, node: &NodeInfo, event: NodeEvent) {
+        if !matches!(event, NodeEvent::Finished { .. } | NodeEvent::Aborted) {
+            return;
+        }
+        // node and its children are done, drop their cursor
+        let prefix = format!("{}.", node.address);
+        self.cursors
+            .live
+            .borrow_mut()
+            .retain(|address, _| **address != *node.address && !address.starts_with(&prefix));
+    }
+}
+
+struct Checkpointed {
+    cursors: Rc<Cursors>,
+    task: BoxAction,
+}
+
+/// Restore outer cursors even if poll panic
+struct CursorsGuard(Option<Rc<Cursors>>);
+
+impl Drop for CursorsGuard {
+    fn drop(&mut self) {
+        let outer = self.0.take();
+        ACTIVE.with(|active| *active.borrow_mut() = outer);
+    }
+}
+
+impl Future for Checkpointed {
+    type Output = RunStatus;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
+        let outer = ACTIVE.with(|active| active.borrow_mut().replace(self.cursors.clone()));
+        let _guard = CursorsGuard(outer);
+        self.task.as_mut().poll(cx)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: Serialize> Serialize for Blackboard<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.get().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Blackboard<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Blackboard::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::Cell,
+        task::{Context, Waker},
+    };
+
+    use super::*;
+    use crate::prelude::*;
+
+    fn counted(name: &'static str, runs: &Rc<Cell<u32>>) -> Composite {
+        let runs = runs.clone();
+        Composite::new(name, move || {
+            runs.set(runs.get() + 1);
+            Box::pin(async { RunStatus::Success })
+        })
+    }
+
+    fn poll_times(task: &mut BoxAction, times: usize) -> Poll<RunStatus> {
+        let mut cx = Context::from_waker(Waker::noop());
+        for _ in 0..times {
+            if let Poll::Ready(status) = task.as_mut().poll(&mut cx) {
+                return Poll::Ready(status);
+            }
+        }
+        Poll::Pending
+    }
+
+    #[test]
+    fn resume_at_same_leaf() {
+        let first = Rc::new(Cell::new(0));
+        let second = Rc::new(Cell::new(0));
+        let released = Rc::new(Cell::new(false));
+        let blackboard = Blackboard::new(5_u32);
+
+        let tree: Composite = Sequence::new([
+            counted("first", &first),
+            PrioritySelector::new([
+                Composite::new("fail", || Box::pin(async { RunStatus::Failure })),
+                Sequence::new([
+                    counted("second", &second),
+                    Composite::resumable("download", {
+                        let released = released.clone();
+                        move |saved: Option<String>| -> BoxAction {
+                            let released = released.clone();
+                            let mut offset: u32 = saved.and_then(|s| s.parse().ok()).unwrap_or(0);
+                            Box::pin(std::future::poll_fn(move |_| {
+                                offset += 1;
+                                save_progress(offset.to_string());
+                                if released.get() {
+                                    Poll::Ready(RunStatus::Success)
+                                } else {
+                                    Poll::Pending
+                                }
+                            }))
+                        }
+                    }),
+                ])
+                .into(),
+            ])
+            .into(),
+        ])
+        .into();
+
+        let checkpointer = Checkpointer::new();
+        let mut task = checkpointer.run(&tree);
+        assert!(poll_times(&mut task, 10).is_pending());
+        let snapshot = checkpointer.snapshot_with(&blackboard);
+        assert_eq!(snapshot.nodes["0"].index, Some(1));
+        assert_eq!(snapshot.nodes["0.1"].index, Some(1));
+        assert_eq!(snapshot.nodes["0.1.1"].index, Some(1));
+        let saved_offset: u32 = snapshot.nodes["0.1.1.1"]
+            .progress
+            .as_ref()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(saved_offset > 0);
+        assert_eq!(snapshot.blackboard, 5);
+        drop(task);
+
+        #[cfg(feature = "serde")]
+        let snapshot: TreeSnapshot<u32> =
+            serde_json::from_str(&serde_json::to_string(&snapshot).unwrap()).unwrap();
+
+        // restart
+        released.set(true);
+        let checkpointer = Checkpointer::new();
+        let mut task = checkpointer.resume(&tree, &snapshot);
+        assert_eq!(poll_times(&mut task, 10), Poll::Ready(RunStatus::Success));
+        assert_eq!((first.get(), second.get()), (1, 1));
+        assert_eq!(checkpointer.snapshot(), TreeSnapshot::default());
+
+        // resumed download continued from saved offset
+        let checkpointer = Checkpointer::new();
+        released.set(false);
+        let mut task = checkpointer.resume(&tree, &snapshot);
+        assert!(poll_times(&mut task, 1).is_pending());
+        let resumed = checkpointer.snapshot().nodes["0.1.1.1"].progress.clone();
+        assert_eq!(resumed, Some((saved_offset + 1).to_string()));
+    }
+
+    #[test]
+    fn resume_runs_of_bounded_repeat() {
+        let attempts = Rc::new(Cell::new(0));
+        let blocked = Rc::new(Cell::new(true));
+        let attempt = {
+            let attempts = attempts.clone();
+            let blocked = blocked.clone();
+            Composite::new("attempt", move || {
+                attempts.set(attempts.get() + 1);
+                // second attempt hang until released
+                let pending = blocked.get() && attempts.get() == 2;
+                Box::pin(std::future::poll_fn(move |_| match pending {
+                    true => Poll::Pending,
+                    false => Poll::Ready(RunStatus::Failure),
+                }))
+            })
+        };
+        let tree: Composite = UntilSuccess::new(attempt).with_max_runs(3).into();
+
+        let checkpointer = Checkpointer::new();
+        let mut task = checkpointer.run(&tree);
+        assert!(poll_times(&mut task, 10).is_pending());
+        let snapshot = checkpointer.snapshot();
+        assert_eq!(snapshot.nodes["0"].runs, Some(1));
+        drop(task);
+
+        // restart, only runs left are done
+        attempts.set(0);
+        blocked.set(false);
+        let mut task = Checkpointer::new().resume(&tree, &snapshot);
+        assert_eq!(poll_times(&mut task, 10), Poll::Ready(RunStatus::Failure));
+        assert_eq!(attempts.get(), 2);
+    }
+}
@@ -0,0 +1,201 @@
+use std::{cell::RefCell, collections::BTreeMap, fmt::Write, rc::Rc, time::Duration};
+
+use crate::{
+    composite::{BoxAction, Composite},
+    instrument::{self, NodeEvent, NodeInfo, Observer},
+    RunStatus,
+};
+
+/// Statistics of every node at same path
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct NodeStats {
+    /// Names from root to this node joined by ';'
+    pub path: String,
+    pub name: String,
+    /// Number of time node started
+    pub runs: u64,
+    pub successes: u64,
+    pub failures: u64,
+    /// Number of poll call, `wake_by_ref` loops make it grow fast
+    pub polls: u64,
+    /// Time spent inside poll (children included)
+    pub busy: Duration,
+    /// Time spent inside poll (children excluded)
+    pub self_busy: Duration,
+    /// Time waiting between polls of finished runs
+    pub pending: Duration,
+    /// Time between first poll and completion of finished runs
+    pub total: Duration,
+}
+
+impl NodeStats {
+    /// Mean time between first poll and completion
+    pub fn average_total(&self) -> Duration {
+        let finished = self.successes + self.failures;
+        if finished == 0 {
+            return Duration::ZERO;
+        }
+        self.total / finished as u32
+    }
+}
+
+/// Snapshot of profiler statistics
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ProfileReport {
+    nodes: BTreeMap<String, NodeStats>,
+}
+
+impl ProfileReport {
+    /// Stats of node at path, like "Sequence;PrioritySelector;Action"
+    pub fn get(&self, path: &str) -> Option<&NodeStats> {
+        self.nodes.get(path)
+    }
+
+    /// All nodes, sorted by path
+    pub fn iter(&self) -> impl Iterator<Item = &NodeStats> {
+        self.nodes.values()
+    }
+
+    /// Nodes with same name, wherever they are in tree
+    pub fn by_name<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a NodeStats> {
+        self.iter().filter(move |stats| stats.name == name)
+    }
+
+    /// Nodes sorted by self busy time, most expensive first
+    pub fn hottest(&self) -> Vec<&NodeStats> {
+        let mut nodes: Vec<_> = self.iter().collect();
+        nodes.sort_by_key(|stats| std::cmp::Reverse(stats.self_busy));
+        nodes
+    }
+
+    /// Folded stack format (`path value` per line) for flamegraph tools
+    /// value is self busy time in microseconds
+    pub fn folded(&self) -> String {
+        let mut out = String::new();
+        for stats in self.iter() {
+            let micros = stats.self_busy.as_micros();
+            if micros > 0 {
+                let _ = writeln!(out, "{} {}", stats.path, micros);
+            }
+        }
+        out
+    }
+}
+
+/// Opt-in instrumentation for trees
+/// Only trees started by `Profiler::profile` are measured, others run as usual.
+///
+/// let profiler = Profiler::new();
+/// profiler.profile(&tree).await;
+/// println!("{}", profiler.report().folded());
+#[derive(Default, Clone)]
+pub struct Profiler {
+    nodes: Rc<RefCell<BTreeMap<String, NodeStats>>>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create task of root, every node started by it is measured
+    pub fn profile(&self, root: &Composite) -> BoxAction {
+        instrument::scope(Rc::new(self.clone()), root)
+    }
+
+    pub fn report(&self) -> ProfileReport {
+        ProfileReport {
+            nodes: self.nodes.borrow().clone(),
+        }
+    }
+
+    pub fn reset(&self) {
+        self.nodes.borrow_mut().clear();
+    }
+}
+
+impl Observer for Profiler {
+    fn on_event(&self, node: &NodeInfo, event: NodeEvent) {
+        let mut nodes = self.nodes.borrow_mut();
+        let stats = nodes
+            .entry(node.path.to_string())
+            .or_insert_with(|| NodeStats {
+                path: node.path.to_string(),
+                name: node.name.clone(),
+                ..Default::default()
+            });
+        match event {
+            NodeEvent::Started => stats.runs += 1,
+            NodeEvent::Polled {
+                busy, self_busy, ..
+            } => {
+                stats.polls += 1;
+                stats.busy += busy;
+                stats.self_busy += self_busy;
+            }
+            NodeEvent::Finished {
+                status,
+                total,
+                busy,
+            } => {
+                match status {
+                    RunStatus::Success => stats.successes += 1,
+                    RunStatus::Failure => stats.failures += 1,
+                }
+                stats.total += total;
+                stats.pending += total.saturating_sub(busy);
+            }
+            NodeEvent::Aborted => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    #[tokio::test]
+    async fn profile_count_runs_and_polls() {
+        let tree: Composite = Sequence::new([
+            Composite::new_action(|| Box::pin(async { RunStatus::Success })),
+            UntilSuccess::new(Composite::new("flaky", {
+                let tries = std::rc::Rc::new(std::cell::Cell::new(0));
+                move || {
+                    let tries = tries.clone();
+                    Box::pin(async move {
+                        tries.set(tries.get() + 1);
+                        if tries.get() < 3 {
+                            RunStatus::Failure
+                        } else {
+                            RunStatus::Success
+                        }
+                    })
+                }
+            }))
+            .into(),
+        ])
+        .into();
+
+        let profiler = Profiler::new();
+        assert_eq!(profiler.profile(&tree).await, RunStatus::Success);
+        let report = profiler.report();
+
+        let root = report.get("Sequence").unwrap();
+        assert_eq!((root.runs, root.successes), (1, 1));
+        // instant children and retries are run in same poll
+        assert_eq!(root.polls, 1);
+
+        let flaky = report.get("Sequence;UntilSuccess;flaky").unwrap();
+        assert_eq!((flaky.runs, flaky.successes, flaky.failures), (3, 1, 2));
+        assert_eq!(report.by_name("Action").count(), 1);
+        assert!(report
+            .folded()
+            .lines()
+            .all(|line| line.starts_with("Sequence")));
+
+        // tree run outside profiler is not measured
+        profiler.reset();
+        (tree.task_production)().await;
+        assert_eq!(profiler.report(), ProfileReport::default());
+    }
+}
@@ -0,0 +1,458 @@
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll, Wake, Waker},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    composite::{BoxAction, Composite},
+    RunStatus,
+};
+
+/// Id of agent in a scheduler, never reused
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AgentId(u64);
+
+/// How often an agent is polled, when it was woken
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TickRate {
+    #[default]
+    EveryFrame,
+    /// At most once every n frames (far from player,...)
+    EveryNth(u32),
+    /// Only when `request_tick` is called
+    OnDemand,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AgentStatus {
+    Running,
+    Finished(RunStatus),
+}
+
+/// Agents to poll, filled by wakers (maybe from other threads: SystemClock timer,...)
+type ReadyQueue = Arc<Mutex<VecDeque<AgentId>>>;
+
+struct AgentWaker {
+    id: AgentId,
+    /// Already in ready queue
+    queued: AtomicBool,
+    ready: ReadyQueue,
+}
+
+impl Wake for AgentWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        if !self.queued.swap(true, Ordering::SeqCst) {
+            self.ready.lock().unwrap().push_back(self.id);
+        }
+    }
+}
+
+struct Agent {
+    task: Option<BoxAction>,
+    waker: Arc<AgentWaker>,
+    status: AgentStatus,
+    rate: TickRate,
+    priority: i32,
+    /// Woken and waiting for its tick
+    pending: bool,
+    requested: bool,
+    last_tick: Option<u64>,
+}
+
+impl Agent {
+    fn is_due(&self, frame: u64) -> bool {
+        match self.rate {
+            TickRate::EveryFrame => true,
+            TickRate::EveryNth(n) => self
+                .last_tick
+                .is_none_or(|last| frame - last >= u64::from(n.max(1))),
+            TickRate::OnDemand => self.requested,
+        }
+    }
+}
+
+/// Result of one frame
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FrameReport {
+    /// Number of agent polled
+    pub polled: usize,
+    /// Agents finished during this frame
+    pub finished: Vec<(AgentId, RunStatus)>,
+    /// Agents due but not polled because budget ran out
+    pub deferred: usize,
+}
+
+/// Run many trees (one per NPC,...) on current thread, no runtime needed.
+/// Only agents whose waker fired are polled, each at most once per frame.
+/// Due agents are polled by priority, then least recently polled first,
+/// so agents left out by budget are polled first next frame.
+/// Time-based nodes use deadlines of clock, they stay correct with sparse ticks.
+///
+/// let mut scheduler = AgentScheduler::new().with_poll_budget(500);
+/// let npc = scheduler.add(&tree);
+/// scheduler.set_tick_rate(npc, TickRate::EveryNth(10));
+/// loop {
+///     let report = scheduler.run_frame();
+///     ...
+/// }
+pub struct AgentScheduler {
+    agents: HashMap<AgentId, Agent>,
+    ready: ReadyQueue,
+    /// Woken agents waiting for their tick
+    pending: Vec<AgentId>,
+    frame: u64,
+    next_id: u64,
+    max_polls: Option<usize>,
+    max_time: Option<Duration>,
+}
+
+impl Default for AgentScheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AgentScheduler {
+    pub fn new() -> Self {
+        Self {
+            agents: HashMap::new(),
+            ready: Arc::default(),
+            pending: Vec::new(),
+            frame: 0,
+            next_id: 0,
+            max_polls: None,
+            max_time: None,
+        }
+    }
+
+    /// Poll at most this many agents per frame
+    pub fn with_poll_budget(mut self, max_polls: usize) -> Self {
+        self.max_polls = Some(max_polls.max(1));
+        self
+    }
+
+    /// Stop polling agents once frame took this long.
+    /// At least one agent is polled per frame.
+    pub fn with_time_budget(mut self, max_time: Duration) -> Self {
+        self.max_time = Some(max_time);
+        self
+    }
+
+    /// Start a new agent running root, it is polled on next frame
+    pub fn add(&mut self, root: &Composite) -> AgentId {
+        let id = AgentId(self.next_id);
+        self.next_id += 1;
+        let waker = Arc::new(AgentWaker {
+            id,
+            queued: AtomicBool::new(false),
+            ready: self.ready.clone(),
+        });
+        waker.wake_by_ref();
+        self.agents.insert(
+            id,
+            Agent {
+                task: Some(root.start()),
+                waker,
+                status: AgentStatus::Running,
+                rate: TickRate::EveryFrame,
+                priority: 0,
+                pending: false,
+                requested: false,
+                last_tick: None,
+            },
+        );
+        id
+    }
+
+    /// Can be changed at any time, applied from next frame
+    pub fn set_tick_rate(&mut self, id: AgentId, rate: TickRate) -> bool {
+        let Some(agent) = self.agents.get_mut(&id) else {
+            return false;
+        };
+        agent.rate = rate;
+        true
+    }
+
+    pub fn tick_rate(&self, id: AgentId) -> Option<TickRate> {
+        self.agents.get(&id).map(|agent| agent.rate)
+    }
+
+    /// Due agents with higher priority are polled first (default 0)
+    pub fn set_priority(&mut self, id: AgentId, priority: i32) -> bool {
+        let Some(agent) = self.agents.get_mut(&id) else {
+            return false;
+        };
+        agent.priority = priority;
+        true
+    }
+
+    pub fn priority(&self, id: AgentId) -> Option<i32> {
+        self.agents.get(&id).map(|agent| agent.priority)
+    }
+
+    /// Poll agent on next frame, whatever its tick rate
+    pub fn request_tick(&mut self, id: AgentId) -> bool {
+        let Some(agent) = self.agents.get_mut(&id) else {
+            return false;
+        };
+        agent.requested = true;
+        agent.waker.wake_by_ref();
+        true
+    }
+
+    /// Drop agent, its running task is dropped too
+    pub fn remove(&mut self, id: AgentId) -> Option<AgentStatus> {
+        self.agents.remove(&id).map(|agent| agent.status)
+    }
+
+    pub fn contains(&self, id: AgentId) -> bool {
+        self.agents.contains_key(&id)
+    }
+
+    /// Finished agents are kept with their status until removed
+    pub fn status(&self, id: AgentId) -> Option<AgentStatus> {
+        self.agents.get(&id).map(|agent| agent.status)
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = AgentId> + '_ {
+        self.agents.keys().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.agents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.agents.is_empty()
+    }
+
+    /// Number of agent still running
+    pub fn running(&self) -> usize {
+        self.agents
+            .values()
+            .filter(|agent| agent.status == AgentStatus::Running)
+            .count()
+    }
+
+    /// Some agent was woken and wait for a tick
+    pub fn has_ready(&self) -> bool {
+        !self.pending.is_empty() || !self.ready.lock().unwrap().is_empty()
+    }
+
+    /// Number of frame run
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Poll due agents woken since their last poll, within budget
+    pub fn run_frame(&mut self) -> FrameReport {
+        let begin = Instant::now();
+        self.frame += 1;
+        let frame = self.frame;
+        // agents woken during this frame wait for next one,
+        // so an agent waking itself can not starve others
+        let woken: Vec<_> = self.ready.lock().unwrap().drain(..).collect();
+        for id in woken {
+            // removed agent can still be queued
+            let Some(agent) = self.agents.get_mut(&id) else {
+                continue;
+            };
+            agent.waker.queued.store(false, Ordering::SeqCst);
+            if agent.task.is_some() && !agent.pending {
+                agent.pending = true;
+                self.pending.push(id);
+            }
+        }
+        self.pending.retain(|id| self.agents.contains_key(id));
+
+        let mut due: Vec<_> = self
+            .pending
+            .iter()
+            .map(|id| (*id, &self.agents[id]))
+            .filter(|(_, agent)| agent.is_due(frame))
+            .map(|(id, agent)| (Reverse(agent.priority), agent.last_tick, id))
+            .collect();
+        due.sort_unstable();
+
+        let mut report = FrameReport::default();
+        for (index, (_, _, id)) in due.iter().enumerate() {
+            if self.max_polls.is_some_and(|max| report.polled >= max)
+                || (report.polled > 0 && self.max_time.is_some_and(|max| begin.elapsed() >= max))
+            {
+                report.deferred = due.len() - index;
+                break;
+            }
+            let agent = self.agents.get_mut(id).unwrap();
+            agent.pending = false;
+            agent.requested = false;
+            agent.last_tick = Some(frame);
+            let Some(task) = agent.task.as_mut() else {
+                continue;
+            };
+
+            report.polled += 1;
+            let waker = Waker::from(agent.waker.clone());
+            let mut cx = Context::from_waker(&waker);
+            if let Poll::Ready(status) = task.as_mut().poll(&mut cx) {
+                agent.task = None;
+                agent.status = AgentStatus::Finished(status);
+                report.finished.push((*id, status));
+            }
+        }
+        let agents = &self.agents;
+        self.pending.retain(|id| agents[id].pending);
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::*;
+    use crate::{clock, prelude::*, testing::VirtualClock};
+
+    /// Agent waking itself on every poll, finishing after `polls` polls
+    fn busy(count: &Rc<Cell<u32>>, polls: u32) -> Composite {
+        let count = count.clone();
+        Composite::new("busy", move || {
+            let count = count.clone();
+            let mut left = polls;
+            Box::pin(std::future::poll_fn(move |cx| {
+                count.set(count.get() + 1);
+                left -= 1;
+                if left == 0 {
+                    return Poll::Ready(RunStatus::Success);
+                }
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }))
+        })
+    }
+
+    #[test]
+    fn round_robin_within_budget() {
+        let (a, b, c) = (Rc::default(), Rc::default(), Rc::default());
+        let mut scheduler = AgentScheduler::new().with_poll_budget(2);
+        let id_a = scheduler.add(&busy(&a, 3));
+        let id_b = scheduler.add(&busy(&b, 1));
+        let id_c = scheduler.add(&busy(&c, 3));
+
+        let report = scheduler.run_frame();
+        assert_eq!((report.polled, report.deferred), (2, 1));
+        assert_eq!(report.finished, [(id_b, RunStatus::Success)]);
+        assert_eq!((a.get(), b.get(), c.get()), (1, 1, 0));
+
+        // deferred agent first, then a woken during last frame
+        scheduler.run_frame();
+        assert_eq!((a.get(), c.get()), (2, 1));
+
+        while scheduler.running() > 0 {
+            scheduler.run_frame();
+        }
+        assert_eq!((a.get(), b.get(), c.get()), (3, 1, 3));
+        assert_eq!(
+            scheduler.status(id_a),
+            Some(AgentStatus::Finished(RunStatus::Success))
+        );
+        assert!(!scheduler.has_ready());
+
+        assert!(scheduler.remove(id_c).is_some());
+        assert!(!scheduler.contains(id_c));
+        assert_eq!(scheduler.len(), 2);
+    }
+
+    #[test]
+    fn idle_agent_is_not_polled() {
+        let polls = Rc::new(Cell::new(0));
+        let tree = Composite::new("idle", {
+            let polls = polls.clone();
+            move || {
+                let polls = polls.clone();
+                Box::pin(std::future::poll_fn(move |_| {
+                    polls.set(polls.get() + 1);
+                    Poll::Pending
+                }))
+            }
+        });
+        let mut scheduler = AgentScheduler::new();
+        let id = scheduler.add(&tree);
+        for _ in 0..10 {
+            scheduler.run_frame();
+        }
+        assert_eq!(polls.get(), 1);
+        assert_eq!(scheduler.status(id), Some(AgentStatus::Running));
+
+        // removed agent woken later is skipped
+        let count = Rc::default();
+        let removed = scheduler.add(&busy(&count, 5));
+        scheduler.remove(removed);
+        assert_eq!(scheduler.run_frame().polled, 0);
+    }
+
+    #[test]
+    fn tick_rates_and_priority() {
+        let (far, near, manual) = (Rc::default(), Rc::default(), Rc::default());
+        let mut scheduler = AgentScheduler::new();
+        let far_id = scheduler.add(&busy(&far, 100));
+        scheduler.add(&busy(&near, 100));
+        let manual_id = scheduler.add(&busy(&manual, 100));
+        scheduler.set_tick_rate(far_id, TickRate::EveryNth(3));
+        scheduler.set_tick_rate(manual_id, TickRate::OnDemand);
+        for _ in 0..7 {
+            scheduler.run_frame();
+        }
+        // far polled at frames 1, 4, 7
+        assert_eq!((far.get(), near.get(), manual.get()), (3, 7, 0));
+
+        scheduler.request_tick(manual_id);
+        scheduler.run_frame();
+        scheduler.run_frame();
+        assert_eq!(manual.get(), 1);
+
+        // changed at runtime
+        scheduler.set_tick_rate(far_id, TickRate::EveryFrame);
+        scheduler.run_frame();
+        assert_eq!(far.get(), 4);
+
+        let mut scheduler = AgentScheduler::new().with_poll_budget(1);
+        let low = scheduler.add(&busy(&Rc::default(), 1));
+        let high = scheduler.add(&busy(&Rc::default(), 1));
+        scheduler.set_priority(high, 10);
+        assert_eq!(scheduler.priority(high), Some(10));
+        assert_eq!(scheduler.run_frame().finished, [(high, RunStatus::Success)]);
+        assert_eq!(scheduler.run_frame().finished, [(low, RunStatus::Success)]);
+    }
+
+    #[test]
+    fn timeout_with_sparse_ticks() {
+        let clock = VirtualClock::new();
+        let tree: Composite =
+            Timeout::new(Duration::from_secs(2), Wait::new(Duration::from_secs(3))).into();
+        let root = Composite::new("npc", {
+            let clock = clock.clone();
+            move || clock::scoped(Rc::new(clock.clone()), tree.start())
+        });
+        let mut scheduler = AgentScheduler::new();
+        let id = scheduler.add(&root);
+        scheduler.set_tick_rate(id, TickRate::OnDemand);
+        scheduler.request_tick(id);
+        assert_eq!(scheduler.run_frame().polled, 1);
+
+        // both deadlines passed before next tick, timeout was first
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(scheduler.run_frame().polled, 0);
+        scheduler.request_tick(id);
+        assert_eq!(scheduler.run_frame().finished, [(id, RunStatus::Failure)]);
+    }
+}
@@ -0,0 +1,385 @@
+use std::{collections::HashMap, fmt, rc::Rc, str::FromStr};
+
+use crate::composite::Composite;
+
+/// Parameters given to a subtree when it is built
+/// Value `{key}` is remapped to param `key` of the subtree holding the reference
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SubtreeParams(HashMap<String, String>);
+
+impl SubtreeParams {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.set(key, value);
+        self
+    }
+
+    pub fn set(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.0.insert(key.into(), value.into());
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// Resolve `{key}` values from parent params.
+    /// `lenient`: a missing parent param is left out instead of an error
+    fn remap(
+        &self,
+        parent: &SubtreeParams,
+        subtree: &str,
+        lenient: bool,
+    ) -> Result<Self, SubtreeError> {
+        let mut remapped = Self::new();
+        for (key, value) in self.iter() {
+            let value = match value.strip_prefix('{').and_then(|v| v.strip_suffix('}')) {
+                Some(parent_key) => match parent.get(parent_key) {
+                    Some(value) => value,
+                    None if lenient => continue,
+                    None => {
+                        return Err(SubtreeError::MissingParam {
+                            subtree: subtree.into(),
+                            param: parent_key.into(),
+                        })
+                    }
+                },
+                None => value,
+            };
+            remapped.set(key, value);
+        }
+        Ok(remapped)
+    }
+}
+
+/// Reference to a subtree registered in SubtreeLibrary,
+/// built into a node by `ctx.subtree(&reference)` or `library.build(&reference)`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubtreeRef {
+    name: String,
+    params: SubtreeParams,
+}
+
+impl SubtreeRef {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            params: SubtreeParams::new(),
+        }
+    }
+
+    /// Overwrite a param of referenced subtree
+    /// `"{key}"` take value from param `key` of current subtree
+    pub fn param(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.params.set(key, value);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubtreeError {
+    /// No subtree registered with this name
+    NotFound(String),
+    /// Subtree reference itself, contain the reference chain
+    Recursive(Vec<String>),
+    MissingParam {
+        subtree: String,
+        param: String,
+    },
+    InvalidParam {
+        subtree: String,
+        param: String,
+        value: String,
+    },
+}
+
+impl fmt::Display for SubtreeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SubtreeError::NotFound(name) => write!(f, "subtree `{name}` is not registered"),
+            SubtreeError::Recursive(chain) => {
+                write!(f, "recursive subtree reference: {}", chain.join(" -> "))
+            }
+            SubtreeError::MissingParam { subtree, param } => {
+                write!(f, "subtree `{subtree}` missing param `{param}`")
+            }
+            SubtreeError::InvalidParam {
+                subtree,
+                param,
+                value,
+            } => write!(
+                f,
+                "subtree `{subtree}` got invalid value `{value}` for `{param}`"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SubtreeError {}
+
+/// Value tried for a missing param by `validate`
+const PLACEHOLDERS: [&str; 3] = ["0", "false", ""];
+
+type SubtreeBuilder = Rc<dyn Fn(&mut SubtreeContext) -> Result<Composite, SubtreeError>>;
+
+#[derive(Clone)]
+struct SubtreeDef {
+    defaults: SubtreeParams,
+    builder: SubtreeBuilder,
+}
+
+/// Given to subtree builder, use it for read params
+/// and for build another subtree inside this one
+pub struct SubtreeContext<'a> {
+    library: &'a SubtreeLibrary,
+    chain: Vec<String>,
+    params: SubtreeParams,
+    /// Built by `validate`, missing params get placeholder values
+    validating: bool,
+}
+
+impl SubtreeContext<'_> {
+    /// Name of subtree being built
+    pub fn name(&self) -> &str {
+        self.chain.last().map(String::as_str).unwrap_or_default()
+    }
+
+    pub fn params(&self) -> &SubtreeParams {
+        &self.params
+    }
+
+    pub fn param(&self, key: &str) -> Option<&str> {
+        self.params.get(key)
+    }
+
+    /// Get param and parse it, error if missing or can not parse
+    pub fn require<T: FromStr>(&self, key: &str) -> Result<T, SubtreeError> {
+        let Some(value) = self.param(key) else {
+            if self.validating {
+                if let Some(value) = PLACEHOLDERS.iter().find_map(|value| value.parse().ok()) {
+                    return Ok(value);
+                }
+            }
+            return Err(SubtreeError::MissingParam {
+                subtree: self.name().into(),
+                param: key.into(),
+            });
+        };
+        value.parse().map_err(|_| SubtreeError::InvalidParam {
+            subtree: self.name().into(),
+            param: key.into(),
+            value: value.into(),
+        })
+    }
+
+    /// Build referenced subtree, its `{key}` params are remapped from this subtree params
+    pub fn subtree(&mut self, reference: &SubtreeRef) -> Result<Composite, SubtreeError> {
+        let params = reference
+            .params
+            .remap(&self.params, self.name(), self.validating)?;
+        self.library
+            .build_inner(&reference.name, params, &self.chain, self.validating)
+    }
+}
+
+/// Registry of named subtrees
+/// A subtree is built from its builder each time it is referenced,
+/// so each reference can have its own params
+#[derive(Default, Clone)]
+pub struct SubtreeLibrary {
+    subtrees: HashMap<String, SubtreeDef>,
+}
+
+impl SubtreeLibrary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        builder: impl Fn(&mut SubtreeContext) -> Result<Composite, SubtreeError> + 'static,
+    ) -> &mut Self {
+        self.register_with_params(name, SubtreeParams::new(), builder)
+    }
+
+    /// Register with default params, reference params overwrite them
+    pub fn register_with_params(
+        &mut self,
+        name: impl Into<String>,
+        defaults: SubtreeParams,
+        builder: impl Fn(&mut SubtreeContext) -> Result<Composite, SubtreeError> + 'static,
+    ) -> &mut Self {
+        self.subtrees.insert(
+            name.into(),
+            SubtreeDef {
+                defaults,
+                builder: Rc::new(builder),
+            },
+        );
+        self
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.subtrees.contains_key(name)
+    }
+
+    /// Build tree from a reference
+    pub fn build(&self, reference: &SubtreeRef) -> Result<Composite, SubtreeError> {
+        self.build_inner(&reference.name, reference.params.clone(), &[], false)
+    }
+
+    /// Build every registered subtree to catch unknown and recursive references before running.
+    /// Missing params get a placeholder value ("0", "false" or ""), so builders
+    /// requiring a param still reach their references.
+    /// It is a best effort: a builder branching on a param, or requiring a type none
+    /// of the placeholders parse into, is only checked up to there.
+    /// Param errors are ignored here, they depend on the reference.
+    pub fn validate(&self) -> Result<(), SubtreeError> {
+        for name in self.subtrees.keys() {
+            if let Err(err @ (SubtreeError::NotFound(_) | SubtreeError::Recursive(_))) =
+                self.build_inner(name, SubtreeParams::new(), &[], true)
+            {
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    fn build_inner(
+        &self,
+        name: &str,
+        params: SubtreeParams,
+        chain: &[String],
+        validating: bool,
+    ) -> Result<Composite, SubtreeError> {
+        let mut chain = chain.to_vec();
+        let recursive = chain.iter().any(|parent| parent == name);
+        chain.push(name.into());
+        if recursive {
+            return Err(SubtreeError::Recursive(chain));
+        }
+
+        let def = self
+            .subtrees
+            .get(name)
+            .ok_or_else(|| SubtreeError::NotFound(name.into()))?;
+        let mut merged = def.defaults.clone();
+        for (key, value) in params.iter() {
+            merged.set(key, value);
+        }
+
+        let mut context = SubtreeContext {
+            library: self,
+            chain,
+            params: merged,
+            validating,
+        };
+        (def.builder)(&mut context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use crate::prelude::*;
+
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    fn leaf(log: &Log, name: String) -> Composite {
+        let log = log.clone();
+        Composite::new(name.clone(), move || {
+            log.borrow_mut().push(name.clone());
+            Box::pin(async { RunStatus::Success })
+        })
+    }
+
+    #[tokio::test]
+    async fn remap_params_per_reference() {
+        let log = Log::default();
+        let mut library = SubtreeLibrary::new();
+        let patrol_log = log.clone();
+        library
+            .register_with_params(
+                "patrol",
+                SubtreeParams::new().with("speed", "1"),
+                move |ctx| {
+                    let speed: f32 = ctx.require("speed")?;
+                    Ok(leaf(&patrol_log, format!("patrol {speed}")))
+                },
+            )
+            .register("guard", |ctx| {
+                Ok(Sequence::new([
+                    ctx.subtree(&SubtreeRef::new("patrol"))?,
+                    ctx.subtree(&SubtreeRef::new("patrol").param("speed", "{run}"))?,
+                ])
+                .into())
+            });
+
+        let root = library
+            .build(&SubtreeRef::new("guard").param("run", "2.5"))
+            .unwrap();
+        assert_eq!(root.name, "Sequence");
+        assert_eq!((root.task_production)().await, RunStatus::Success);
+        assert_eq!(*log.borrow(), ["patrol 1", "patrol 2.5"]);
+
+        let missing = library.build(&SubtreeRef::new("guard"));
+        assert_eq!(
+            missing.err(),
+            Some(SubtreeError::MissingParam {
+                subtree: "guard".into(),
+                param: "run".into()
+            })
+        );
+    }
+
+    #[test]
+    fn detect_recursive_reference() {
+        let mut library = SubtreeLibrary::new();
+        library
+            .register("a", |ctx| ctx.subtree(&SubtreeRef::new("b")))
+            .register("b", |ctx| {
+                Ok(Sequence::new([ctx.subtree(&SubtreeRef::new("a"))?]).into())
+            });
+
+        let err = library.validate().unwrap_err();
+        assert!(matches!(err, SubtreeError::Recursive(chain) if chain.len() == 3));
+        assert!(matches!(
+            library.build(&SubtreeRef::new("b")),
+            Err(SubtreeError::Recursive(_))
+        ));
+        assert_eq!(
+            library.build(&SubtreeRef::new("c")).err(),
+            Some(SubtreeError::NotFound("c".into()))
+        );
+
+        // params are required before the reference, still found
+        let mut library = SubtreeLibrary::new();
+        library
+            .register("walk", |ctx| {
+                let _speed: f32 = ctx.require("speed")?;
+                let _loop: bool = ctx.require("loop")?;
+                ctx.subtree(&SubtreeRef::new("run").param("speed", "{speed}"))
+            })
+            .register("run", |ctx| {
+                let _speed: f32 = ctx.require("speed")?;
+                let _target: String = ctx.require("target")?;
+                ctx.subtree(&SubtreeRef::new("walk").param("target", "{target}"))
+            });
+        let err = library.validate().unwrap_err();
+        assert!(matches!(err, SubtreeError::Recursive(chain) if chain.len() == 3));
+    }
+}
@@ -0,0 +1,293 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll, Wake, Waker},
+    time::Duration,
+};
+
+use crate::{
+    clock::{self, Clock, TimerId},
+    composite::{BoxAction, Composite},
+    instrument::{self, NodeEvent, NodeInfo, Observer},
+    RunStatus,
+};
+
+#[derive(Default)]
+struct VirtualTime {
+    now: Duration,
+    timers: Vec<(Duration, TimerId, Waker)>,
+    next_id: u64,
+}
+
+/// Clock only moving when told to, for deterministic test of time-based nodes.
+/// Clone of clock share same time.
+#[derive(Default, Clone)]
+pub struct VirtualClock(Rc<RefCell<VirtualTime>>);
+
+impl VirtualClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Move time forward and wake every timer reached
+    pub fn advance(&self, by: Duration) {
+        let due: Vec<_> = {
+            let mut time = self.0.borrow_mut();
+            time.now += by;
+            let now = time.now;
+            let (due, waiting) = time
+                .timers
+                .drain(..)
+                .partition(|(deadline, _, _)| *deadline <= now);
+            time.timers = waiting;
+            due
+        };
+        for (_, _, waker) in due {
+            waker.wake();
+        }
+    }
+
+    /// Earliest deadline waiting to be reached
+    pub fn next_deadline(&self) -> Option<Duration> {
+        let time = self.0.borrow();
+        time.timers.iter().map(|(deadline, _, _)| *deadline).min()
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> Duration {
+        self.0.borrow().now
+    }
+
+    fn wake_at(&self, deadline: Duration, waker: &Waker) -> Option<TimerId> {
+        let mut time = self.0.borrow_mut();
+        if deadline <= time.now {
+            waker.wake_by_ref();
+            return None;
+        }
+        time.next_id += 1;
+        let id = TimerId(time.next_id);
+        time.timers.push((deadline, id, waker.clone()));
+        Some(id)
+    }
+
+    fn cancel(&self, timer: TimerId) {
+        self.0.borrow_mut().timers.retain(|(_, id, _)| *id != timer);
+    }
+}
+
+#[derive(Default)]
+struct WokenFlag(AtomicBool);
+
+impl Wake for WokenFlag {
+    fn wake(self: Arc<Self>) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Remember deepest node still pending after a poll of root
+#[derive(Default)]
+struct ActiveNode {
+    path: RefCell<Option<Rc<str>>>,
+    found: Cell<bool>,
+}
+
+impl Observer for ActiveNode {
+    fn on_event(&self, node: &NodeInfo, event: NodeEvent) {
+        // children report their poll before their parent,
+        // so first pending node of a root poll is the deepest one
+        if let NodeEvent::Polled { pending: true, .. } = event {
+            if !self.found.replace(true) {
+                *self.path.borrow_mut() = Some(node.path.clone());
+            }
+        }
+    }
+}
+
+/// Single task executor driving a tree with a VirtualClock.
+/// It poll only when the tree was woken, so time-based nodes
+/// only progress when test advance the clock.
+///
+/// let mut executor = StepExecutor::new(&tree);
+/// executor.run_until_stalled();
+/// assert_eq!(executor.active_name().as_deref(), Some("Wait"));
+/// executor.advance(Duration::from_secs(1));
+pub struct StepExecutor {
+    task: BoxAction,
+    clock: VirtualClock,
+    woken: Arc<WokenFlag>,
+    active: Rc<ActiveNode>,
+    status: Option<RunStatus>,
+    polls: u64,
+    max_polls_per_step: u64,
+}
+
+impl StepExecutor {
+    pub fn new(root: &Composite) -> Self {
+        Self::with_clock(root, VirtualClock::new())
+    }
+
+    pub fn with_clock(root: &Composite, clock: VirtualClock) -> Self {
+        let active = Rc::new(ActiveNode::default());
+        let task = clock::scoped(
+            Rc::new(clock.clone()),
+            instrument::scope(active.clone(), root),
+        );
+        let woken = Arc::new(WokenFlag::default());
+        // first step always poll
+        woken.0.store(true, Ordering::SeqCst);
+        Self {
+            task,
+            clock,
+            woken,
+            active,
+            status: None,
+            polls: 0,
+            max_polls_per_step: 10_000,
+        }
+    }
+
+    /// Nodes waking themselves on every poll (InterruptAction,...) never stall,
+    /// a step stop after this many polls (default 10_000)
+    pub fn with_max_polls_per_step(mut self, max_polls: u64) -> Self {
+        self.max_polls_per_step = max_polls.max(1);
+        self
+    }
+
+    pub fn clock(&self) -> &VirtualClock {
+        &self.clock
+    }
+
+    /// Poll tree while it keep waking itself
+    /// Return status if tree finished
+    pub fn run_until_stalled(&mut self) -> Option<RunStatus> {
+        let waker = Waker::from(self.woken.clone());
+        let mut cx = Context::from_waker(&waker);
+        for _ in 0..self.max_polls_per_step {
+            if self.status.is_some() || !self.woken.0.swap(false, Ordering::SeqCst) {
+                break;
+            }
+            self.active.found.set(false);
+            self.polls += 1;
+            if let Poll::Ready(status) = self.task.as_mut().poll(&mut cx) {
+                self.status = Some(status);
+                self.active.path.borrow_mut().take();
+            }
+        }
+        self.status
+    }
+
+    /// Move clock forward then run until stalled
+    pub fn advance(&mut self, by: Duration) -> Option<RunStatus> {
+        self.clock.advance(by);
+        self.run_until_stalled()
+    }
+
+    /// Jump clock from deadline to deadline until tree finish
+    /// Panic if tree stall without any timer (it would never finish)
+    pub fn run_to_completion(&mut self) -> RunStatus {
+        loop {
+            if let Some(status) = self.run_until_stalled() {
+                return status;
+            }
+            let next = self
+                .clock
+                .next_deadline()
+                .expect("tree is stalled and no timer is waiting");
+            let by = next.saturating_sub(self.clock.now());
+            self.advance(by);
+        }
+    }
+
+    pub fn status(&self) -> Option<RunStatus> {
+        self.status
+    }
+
+    /// Path (names joined by ';') of deepest node pending after last poll
+    pub fn active(&self) -> Option<String> {
+        self.active.path.borrow().as_deref().map(Into::into)
+    }
+
+    /// Name of deepest node pending after last poll
+    pub fn active_name(&self) -> Option<String> {
+        let active = self.active()?;
+        active.rsplit(';').next().map(Into::into)
+    }
+
+    /// Number of time root was polled
+    pub fn polls(&self) -> u64 {
+        self.polls
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn step_through_time_based_tree() {
+        let tree: Composite = Sequence::new([
+            Wait::new(Duration::from_secs(1)).into(),
+            Composite::new("patrol", || {
+                Box::pin(async {
+                    clock::sleep(Duration::from_secs(10)).await;
+                    RunStatus::Success
+                })
+            }),
+            Timeout::new(Duration::from_secs(2), Wait::new(Duration::from_secs(5))).into(),
+        ])
+        .into();
+
+        let mut executor = StepExecutor::new(&tree);
+        assert_eq!(executor.run_until_stalled(), None);
+        assert_eq!(executor.active().as_deref(), Some("Sequence;Wait"));
+
+        // nothing move without time
+        assert_eq!(executor.run_until_stalled(), None);
+        executor.advance(Duration::from_millis(999));
+        assert_eq!(executor.active_name().as_deref(), Some("Wait"));
+
+        executor.advance(Duration::from_millis(1));
+        assert_eq!(executor.active_name().as_deref(), Some("patrol"));
+
+        executor.advance(Duration::from_secs(10));
+        assert_eq!(executor.active().as_deref(), Some("Sequence;Timeout;Wait"));
+
+        assert_eq!(executor.run_to_completion(), RunStatus::Failure);
+        assert_eq!(executor.clock().now(), Duration::from_secs(13));
+        assert_eq!(executor.active(), None);
+    }
+
+    #[test]
+    fn finished_nodes_cancel_their_timers() {
+        // child finish first, timeout is not waited anymore
+        let tree: Composite =
+            Timeout::new(Duration::from_secs(10), Wait::new(Duration::from_secs(1))).into();
+        let mut executor = StepExecutor::new(&tree);
+        assert_eq!(executor.run_until_stalled(), None);
+        assert_eq!(
+            executor.clock().next_deadline(),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(executor.run_to_completion(), RunStatus::Success);
+        assert_eq!(executor.clock().next_deadline(), None);
+
+        // aborted child drop its sleep
+        let tree: Composite =
+            Timeout::new(Duration::from_secs(1), Wait::new(Duration::from_secs(5))).into();
+        let mut executor = StepExecutor::new(&tree);
+        assert_eq!(executor.run_to_completion(), RunStatus::Failure);
+        assert_eq!(executor.clock().next_deadline(), None);
+    }
+}
@@ -0,0 +1,129 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    composite::{BoxAction, Composite},
+    instrument::{self, NodeEvent, NodeInfo, Observer},
+    RunStatus,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraceEvent {
+    /// Node polled first time
+    Enter(String),
+    /// Node finished with status
+    Exit(String, RunStatus),
+    /// Node dropped before finishing (interrupted, timeout,...)
+    Abort(String),
+}
+
+impl TraceEvent {
+    pub fn name(&self) -> &str {
+        match self {
+            TraceEvent::Enter(name) | TraceEvent::Exit(name, _) | TraceEvent::Abort(name) => name,
+        }
+    }
+}
+
+/// Record ordered entries and exits of every node of a tree, for test
+///
+/// let recorder = TraceRecorder::new();
+/// recorder.record(&tree).await;
+/// assert_trace!(recorder, [
+///     enter "Sequence",
+///     enter "Action",
+///     exit "Action" => Success,
+///     exit "Sequence" => Success,
+/// ]);
+#[derive(Default, Clone)]
+pub struct TraceRecorder {
+    events: Rc<RefCell<Vec<TraceEvent>>>,
+}
+
+impl TraceRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create task of root, every node started by it is recorded
+    pub fn record(&self, root: &Composite) -> BoxAction {
+        instrument::scope(Rc::new(self.clone()), root)
+    }
+
+    pub fn events(&self) -> Vec<TraceEvent> {
+        self.events.borrow().clone()
+    }
+
+    /// Names of entered nodes in order
+    pub fn entered(&self) -> Vec<String> {
+        self.events
+            .borrow()
+            .iter()
+            .filter(|event| matches!(event, TraceEvent::Enter(_)))
+            .map(|event| event.name().into())
+            .collect()
+    }
+
+    /// Node with this name has been entered at least once
+    pub fn has_entered(&self, name: &str) -> bool {
+        self.events
+            .borrow()
+            .iter()
+            .any(|event| matches!(event, TraceEvent::Enter(entered) if entered == name))
+    }
+
+    pub fn clear(&self) {
+        self.events.borrow_mut().clear();
+    }
+}
+
+impl Observer for TraceRecorder {
+    fn on_event(&self, node: &NodeInfo, event: NodeEvent) {
+        let event = match event {
+            NodeEvent::Started => TraceEvent::Enter(node.name.clone()),
+            NodeEvent::Finished { status, .. } => TraceEvent::Exit(node.name.clone(), status),
+            NodeEvent::Aborted => TraceEvent::Abort(node.name.clone()),
+            NodeEvent::Polled { .. } => return,
+        };
+        self.events.borrow_mut().push(event);
+    }
+}
+
+/// Compare recorded trace with expected one
+///
+/// Full trace:
+/// assert_trace!(recorder, [
+///     enter "Sequence",
+///     enter "Action",
+///     exit "Action" => Success,
+///     abort "Wait",
+///     exit "Sequence" => Failure,
+/// ]);
+///
+/// Only order of entered nodes:
+/// assert_trace!(recorder, ["Sequence", "Action"]);
+#[macro_export]
+macro_rules! assert_trace {
+    ($recorder:expr, [$($kind:ident $name:literal $(=> $status:ident)?),* $(,)?]) => {
+        assert_eq!(
+            $recorder.events(),
+            vec![$($crate::__trace_event!($kind $name $(=> $status)?)),*] as Vec<$crate::trace::TraceEvent>
+        )
+    };
+    ($recorder:expr, [$($name:literal),* $(,)?]) => {
+        assert_eq!($recorder.entered(), vec![$($name.to_string()),*] as Vec<String>)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __trace_event {
+    (enter $name:literal) => {
+        $crate::trace::TraceEvent::Enter($name.into())
+    };
+    (exit $name:literal => $status:ident) => {
+        $crate::trace::TraceEvent::Exit($name.into(), $crate::RunStatus::$status)
+    };
+    (abort $name:literal) => {
+        $crate::trace::TraceEvent::Abort($name.into())
+    };
+}
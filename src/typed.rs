@@ -0,0 +1,476 @@
+//! Statically typed nodes, every node and its task is a concrete type:
+//! no `Rc<dyn Fn>`, no boxed future, so a tree compile like a hand-written state machine.
+//! Convert into `Composite` (`.into()`) to mix it with dynamic nodes.
+//!
+//! use bhv_async::typed::{self, Node};
+//!
+//! let tree = typed::Sequence::new((
+//!     typed::Action::new(|| async { RunStatus::Success }),
+//!     typed::Inverter::new(typed::Action::new(|| async { RunStatus::Failure })),
+//! ));
+//! let status = tree.start().await;
+//! let dynamic: Composite = tree.into();
+
+use std::{future::Future, pin::Pin, task::Poll};
+
+use crate::{common_behaviors::DEFAULT_STEP_LIMIT, composite::Composite, RunStatus};
+
+/// Node with concrete task type
+pub trait Node: Clone + 'static {
+    /// Name and kind of node once converted into Composite
+    const KIND: &'static str;
+
+    type Task: Future<Output = RunStatus> + 'static;
+
+    /// Create new task of this node
+    fn start(&self) -> Self::Task;
+
+    /// Dynamic node running this node, what `.into()` does for built-in nodes
+    #[track_caller]
+    fn into_composite(self) -> Composite {
+        Composite::new(Self::KIND, move || Box::pin(self.start())).with_kind(Self::KIND)
+    }
+}
+
+/// `From` for each node type: a blanket impl over `Node` would make
+/// every failed `Into<Composite>` bound ask for a `Node` impl
+macro_rules! impl_from_node {
+    ($($node:ident<$($param:ident),+>),+ $(,)?) => {$(
+        impl<$($param),+> From<$node<$($param),+>> for Composite
+        where
+            $node<$($param),+>: Node,
+        {
+            #[track_caller]
+            fn from(node: $node<$($param),+>) -> Self {
+                node.into_composite()
+            }
+        }
+    )+};
+}
+
+impl_from_node!(
+    Action<F>,
+    Sequence<C>,
+    PrioritySelector<C>,
+    Decorator<F, C>,
+    Inverter<C>,
+    UntilSuccess<C>,
+    UntilFailure<C>,
+);
+
+/// Leaf running future created by closure
+#[derive(Clone)]
+pub struct Action<F> {
+    task_production: F,
+}
+
+impl<F, Fut> Action<F>
+where
+    F: Fn() -> Fut + Clone + 'static,
+    Fut: Future<Output = RunStatus> + 'static,
+{
+    pub fn new(task_production: F) -> Self {
+        Self { task_production }
+    }
+}
+
+impl<F, Fut> Node for Action<F>
+where
+    F: Fn() -> Fut + Clone + 'static,
+    Fut: Future<Output = RunStatus> + 'static,
+{
+    const KIND: &'static str = "Action";
+    type Task = Fut;
+
+    fn start(&self) -> Self::Task {
+        (self.task_production)()
+    }
+}
+
+/// Future never created, fill unused variants of `OneOf`
+pub enum Never {}
+
+impl Future for Never {
+    type Output = RunStatus;
+
+    fn poll(self: Pin<&mut Self>, _: &mut std::task::Context<'_>) -> Poll<Self::Output> {
+        match *self {}
+    }
+}
+
+/// Task of one child of a tuple
+pub enum OneOf<A, B = Never, C = Never, D = Never, E = Never, F = Never, G = Never, H = Never> {
+    A(A),
+    B(B),
+    C(C),
+    D(D),
+    E(E),
+    F(F),
+    G(G),
+    H(H),
+}
+
+impl<A, B, C, D, E, F, G, H> Future for OneOf<A, B, C, D, E, F, G, H>
+where
+    A: Future<Output = RunStatus>,
+    B: Future<Output = RunStatus>,
+    C: Future<Output = RunStatus>,
+    D: Future<Output = RunStatus>,
+    E: Future<Output = RunStatus>,
+    F: Future<Output = RunStatus>,
+    G: Future<Output = RunStatus>,
+    H: Future<Output = RunStatus>,
+{
+    type Output = RunStatus;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
+        // variant is never moved out while pinned
+        unsafe {
+            match self.get_unchecked_mut() {
+                OneOf::A(task) => Pin::new_unchecked(task).poll(cx),
+                OneOf::B(task) => Pin::new_unchecked(task).poll(cx),
+                OneOf::C(task) => Pin::new_unchecked(task).poll(cx),
+                OneOf::D(task) => Pin::new_unchecked(task).poll(cx),
+                OneOf::E(task) => Pin::new_unchecked(task).poll(cx),
+                OneOf::F(task) => Pin::new_unchecked(task).poll(cx),
+                OneOf::G(task) => Pin::new_unchecked(task).poll(cx),
+                OneOf::H(task) => Pin::new_unchecked(task).poll(cx),
+            }
+        }
+    }
+}
+
+/// Tuple of 1 to 8 nodes
+pub trait Children: Clone + 'static {
+    const LEN: usize;
+
+    type Task: Future<Output = RunStatus> + 'static;
+
+    /// Create task of child at index
+    fn start(&self, index: usize) -> Self::Task;
+}
+
+macro_rules! impl_children {
+    ($len:expr; $($index:tt $name:ident $variant:ident),+) => {
+        impl<$($name: Node),+> Children for ($($name,)+) {
+            const LEN: usize = $len;
+            type Task = OneOf<$($name::Task),+>;
+
+            fn start(&self, index: usize) -> Self::Task {
+                match index {
+                    $($index => OneOf::$variant(self.$index.start()),)+
+                    _ => unreachable!("child index out of tuple"),
+                }
+            }
+        }
+    };
+}
+
+impl_children!(1; 0 T0 A);
+impl_children!(2; 0 T0 A, 1 T1 B);
+impl_children!(3; 0 T0 A, 1 T1 B, 2 T2 C);
+impl_children!(4; 0 T0 A, 1 T1 B, 2 T2 C, 3 T3 D);
+impl_children!(5; 0 T0 A, 1 T1 B, 2 T2 C, 3 T3 D, 4 T4 E);
+impl_children!(6; 0 T0 A, 1 T1 B, 2 T2 C, 3 T3 D, 4 T4 E, 5 T5 F);
+impl_children!(7; 0 T0 A, 1 T1 B, 2 T2 C, 3 T3 D, 4 T4 E, 5 T5 F, 6 T6 G);
+impl_children!(8; 0 T0 A, 1 T1 B, 2 T2 C, 3 T3 D, 4 T4 E, 5 T5 F, 6 T6 G, 7 T7 H);
+
+/// Run children in order, stop at first child with `stop_on` status
+pub struct GroupTask<C: Children> {
+    childs: C,
+    stop_on: RunStatus,
+    index: usize,
+    task: Option<C::Task>,
+}
+
+impl<C: Children> Future for GroupTask<C> {
+    type Output = RunStatus;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
+        // task is only dropped in place, never moved
+        let this = unsafe { self.get_unchecked_mut() };
+        for _ in 0..DEFAULT_STEP_LIMIT {
+            if this.task.is_none() {
+                this.task = Some(this.childs.start(this.index));
+            }
+            let task = unsafe { Pin::new_unchecked(this.task.as_mut().unwrap()) };
+            let Poll::Ready(status) = task.poll(cx) else {
+                return Poll::Pending;
+            };
+            this.task = None;
+            if status == this.stop_on || this.index + 1 >= C::LEN {
+                return Poll::Ready(status);
+            }
+            this.index += 1;
+        }
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+/// Same as dynamic Sequence, children is a tuple
+#[derive(Clone)]
+pub struct Sequence<C> {
+    childs: C,
+}
+
+impl<C: Children> Sequence<C> {
+    pub fn new(childs: C) -> Self {
+        Self { childs }
+    }
+}
+
+impl<C: Children> Node for Sequence<C> {
+    const KIND: &'static str = "Sequence";
+    type Task = GroupTask<C>;
+
+    fn start(&self) -> Self::Task {
+        GroupTask {
+            childs: self.childs.clone(),
+            stop_on: RunStatus::Failure,
+            index: 0,
+            task: None,
+        }
+    }
+}
+
+/// Same as dynamic PrioritySelector, children is a tuple
+#[derive(Clone)]
+pub struct PrioritySelector<C> {
+    childs: C,
+}
+
+impl<C: Children> PrioritySelector<C> {
+    pub fn new(childs: C) -> Self {
+        Self { childs }
+    }
+}
+
+impl<C: Children> Node for PrioritySelector<C> {
+    const KIND: &'static str = "PrioritySelector";
+    type Task = GroupTask<C>;
+
+    fn start(&self) -> Self::Task {
+        GroupTask {
+            childs: self.childs.clone(),
+            stop_on: RunStatus::Success,
+            index: 0,
+            task: None,
+        }
+    }
+}
+
+/// Run child only if condition is met, otherwise return failed.
+/// Condition is checked on every poll like dynamic Decorator.
+#[derive(Clone)]
+pub struct Decorator<F, C> {
+    run_condition: F,
+    child: C,
+}
+
+impl<F: Fn() -> bool + Clone + 'static, C: Node> Decorator<F, C> {
+    pub fn new(run_condition: F, child: C) -> Self {
+        Self {
+            run_condition,
+            child,
+        }
+    }
+}
+
+pub struct DecoratorTask<F, T> {
+    run_condition: F,
+    task: T,
+}
+
+impl<F: Fn() -> bool, T: Future<Output = RunStatus>> Future for DecoratorTask<F, T> {
+    type Output = RunStatus;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
+        let this = unsafe { self.get_unchecked_mut() };
+        if !(this.run_condition)() {
+            return Poll::Ready(RunStatus::Failure);
+        }
+        unsafe { Pin::new_unchecked(&mut this.task) }.poll(cx)
+    }
+}
+
+impl<F: Fn() -> bool + Clone + 'static, C: Node> Node for Decorator<F, C> {
+    const KIND: &'static str = "Decorator";
+    type Task = DecoratorTask<F, C::Task>;
+
+    fn start(&self) -> Self::Task {
+        // child future is lazy, it does nothing until polled
+        DecoratorTask {
+            run_condition: self.run_condition.clone(),
+            task: self.child.start(),
+        }
+    }
+}
+
+/// Success became Failed, Failed became Success
+#[derive(Clone)]
+pub struct Inverter<C> {
+    child: C,
+}
+
+impl<C: Node> Inverter<C> {
+    pub fn new(child: C) -> Self {
+        Self { child }
+    }
+}
+
+pub struct InverterTask<T> {
+    task: T,
+}
+
+impl<T: Future<Output = RunStatus>> Future for InverterTask<T> {
+    type Output = RunStatus;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
+        let task = unsafe { self.map_unchecked_mut(|this| &mut this.task) };
+        match task.poll(cx) {
+            Poll::Ready(RunStatus::Success) => Poll::Ready(RunStatus::Failure),
+            Poll::Ready(RunStatus::Failure) => Poll::Ready(RunStatus::Success),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<C: Node> Node for Inverter<C> {
+    const KIND: &'static str = "Inverter";
+    type Task = InverterTask<C::Task>;
+
+    fn start(&self) -> Self::Task {
+        InverterTask {
+            task: self.child.start(),
+        }
+    }
+}
+
+/// Run child again until it finish with `until` status
+pub struct RepeatTask<C: Node> {
+    child: C,
+    until: RunStatus,
+    task: Option<C::Task>,
+}
+
+impl<C: Node> Future for RepeatTask<C> {
+    type Output = RunStatus;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
+        let this = unsafe { self.get_unchecked_mut() };
+        for _ in 0..DEFAULT_STEP_LIMIT {
+            let task = this.task.get_or_insert_with(|| this.child.start());
+            let Poll::Ready(status) = unsafe { Pin::new_unchecked(task) }.poll(cx) else {
+                return Poll::Pending;
+            };
+            this.task = None;
+            if status == this.until {
+                return Poll::Ready(status);
+            }
+        }
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+/// Run child again until it success
+#[derive(Clone)]
+pub struct UntilSuccess<C> {
+    child: C,
+}
+
+impl<C: Node> UntilSuccess<C> {
+    pub fn new(child: C) -> Self {
+        Self { child }
+    }
+}
+
+impl<C: Node> Node for UntilSuccess<C> {
+    const KIND: &'static str = "UntilSuccess";
+    type Task = RepeatTask<C>;
+
+    fn start(&self) -> Self::Task {
+        RepeatTask {
+            child: self.child.clone(),
+            until: RunStatus::Success,
+            task: None,
+        }
+    }
+}
+
+/// Run child again until it failure
+#[derive(Clone)]
+pub struct UntilFailure<C> {
+    child: C,
+}
+
+impl<C: Node> UntilFailure<C> {
+    pub fn new(child: C) -> Self {
+        Self { child }
+    }
+}
+
+impl<C: Node> Node for UntilFailure<C> {
+    const KIND: &'static str = "UntilFailure";
+    type Task = RepeatTask<C>;
+
+    fn start(&self) -> Self::Task {
+        RepeatTask {
+            child: self.child.clone(),
+            until: RunStatus::Failure,
+            task: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::*;
+    use crate::common_behaviors;
+
+    #[tokio::test]
+    async fn typed_tree_run_like_dynamic() {
+        let tries = Rc::new(Cell::new(0));
+        let flaky = Action::new({
+            let tries = tries.clone();
+            move || {
+                let tries = tries.clone();
+                async move {
+                    tries.set(tries.get() + 1);
+                    match tries.get() < 3 {
+                        true => RunStatus::Failure,
+                        false => RunStatus::Success,
+                    }
+                }
+            }
+        });
+        let tree = Sequence::new((
+            Action::new(|| async { RunStatus::Success }),
+            PrioritySelector::new((
+                Decorator::new(|| false, Action::new(|| async { RunStatus::Success })),
+                Inverter::new(Action::new(|| async { RunStatus::Failure })),
+            )),
+            UntilSuccess::new(flaky),
+        ));
+
+        assert_eq!(tree.start().await, RunStatus::Success);
+        assert_eq!(tries.get(), 3);
+
+        // no allocation needed: task is a plain value
+        let task = tree.start();
+        assert!(std::mem::size_of_val(&task) < 256);
+
+        // mixed with dynamic nodes
+        let dynamic = common_behaviors::Sequence::new([
+            tree.into(),
+            Inverter::new(UntilFailure::new(Action::new(|| async {
+                RunStatus::Failure
+            })))
+            .into(),
+        ]);
+        assert_eq!(dynamic.await, RunStatus::Success);
+    }
+}
@@ -0,0 +1,200 @@
+use std::{future::Future, pin::Pin, rc::Rc, task::Poll};
+
+use crate::{
+    blackboard::Blackboard,
+    composite::{BoxAction, Composite},
+    RunStatus,
+};
+
+/// Can create from Box::pin(an future return score)
+pub type BoxScore = Pin<Box<dyn Future<Output = f32>>>;
+
+#[derive(Clone)]
+enum Scorer {
+    Sync(Rc<dyn Fn() -> f32>),
+    Async(Rc<dyn Fn() -> BoxScore>),
+}
+
+/// A child of UtilitySelector with function give score of it
+#[derive(Clone)]
+pub struct UtilityChild {
+    scorer: Scorer,
+    child: Composite,
+}
+
+impl UtilityChild {
+    #[track_caller]
+    pub fn new(score: impl Fn() -> f32 + 'static, child: impl Into<Composite>) -> Self {
+        Self {
+            scorer: Scorer::Sync(Rc::new(score)),
+            child: child.into(),
+        }
+    }
+
+    /// Score read from blackboard data
+    pub fn with_blackboard<T: 'static>(
+        blackboard: &Blackboard<T>,
+        score: impl Fn(&T) -> f32 + 'static,
+        child: impl Into<Composite>,
+    ) -> Self {
+        let blackboard = blackboard.clone();
+        Self::new(move || blackboard.read(&score), child)
+    }
+
+    /// Score need await something (query, pathfinding,...)
+    #[track_caller]
+    pub fn new_async(score: impl Fn() -> BoxScore + 'static, child: impl Into<Composite>) -> Self {
+        Self {
+            scorer: Scorer::Async(Rc::new(score)),
+            child: child.into(),
+        }
+    }
+}
+
+/// One round of scoring all children
+struct Scoring {
+    scores: Vec<Option<f32>>,
+    pending: Vec<Option<BoxScore>>,
+}
+
+impl Scoring {
+    fn start(childs: &[UtilityChild]) -> Self {
+        let mut scores = Vec::with_capacity(childs.len());
+        let mut pending = Vec::with_capacity(childs.len());
+        for child in childs {
+            match &child.scorer {
+                Scorer::Sync(score) => {
+                    scores.push(Some(score()));
+                    pending.push(None);
+                }
+                Scorer::Async(score) => {
+                    scores.push(None);
+                    pending.push(Some(score()));
+                }
+            }
+        }
+        Self { scores, pending }
+    }
+
+    /// Ready with all scores when every async scorer finished
+    fn poll(&mut self, cx: &mut std::task::Context<'_>) -> Poll<&[Option<f32>]> {
+        for (score, pending) in self.scores.iter_mut().zip(self.pending.iter_mut()) {
+            let Some(fut) = pending else {
+                continue;
+            };
+            if let Poll::Ready(value) = fut.as_mut().poll(cx) {
+                *score = Some(value);
+                pending.take();
+            }
+        }
+        if self.pending.iter().any(Option::is_some) {
+            return Poll::Pending;
+        }
+        Poll::Ready(&self.scores)
+    }
+}
+
+/// Index of highest score, first one win on tie
+fn best_index(scores: &[Option<f32>]) -> usize {
+    let mut best = 0;
+    for (index, score) in scores.iter().enumerate() {
+        if score.unwrap_or(f32::MIN) > scores[best].unwrap_or(f32::MIN) {
+            best = index;
+        }
+    }
+    best
+}
+
+/// An Selector score all children then run the highest one and return its status.
+/// It can be mixed with any other composite, scoring is done when this node start.
+///
+/// With `with_rescore`, children get scored again each time this node is polled
+/// while a child running, and switch to better child if its score beat the
+/// running one by more than margin (the running child is dropped).
+pub struct UtilitySelector {
+    childs: Rc<[UtilityChild]>,
+    switch_margin: Option<f32>,
+    scoring: Option<Scoring>,
+    running: Option<(usize, BoxAction)>,
+}
+
+impl Clone for UtilitySelector {
+    fn clone(&self) -> Self {
+        Self {
+            childs: self.childs.clone(),
+            switch_margin: self.switch_margin,
+            scoring: None,
+            running: None,
+        }
+    }
+}
+
+impl UtilitySelector {
+    pub fn new(childs: impl Into<Rc<[UtilityChild]>>) -> Self {
+        Self {
+            childs: childs.into(),
+            switch_margin: None,
+            scoring: None,
+            running: None,
+        }
+    }
+
+    /// Re-score children while one running,
+    /// switch when another child score higher than running score + margin
+    pub fn with_rescore(mut self, switch_margin: f32) -> Self {
+        self.switch_margin = Some(switch_margin.max(0.0));
+        self
+    }
+
+    fn start_child(&mut self, index: usize) {
+        let child = &self.childs[index].child;
+        #[cfg(feature = "tracing")]
+        tracing::debug!(child = %child.label(), index, "utility start");
+        let fut = child.start_child(index);
+        self.running = Some((index, fut));
+    }
+}
+
+impl Future for UtilitySelector {
+    type Output = RunStatus;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        if this.childs.is_empty() {
+            return Poll::Ready(RunStatus::Failure);
+        }
+
+        let rescore = this.running.is_some() && this.switch_margin.is_some();
+        if this.running.is_none() || rescore {
+            let scoring = this
+                .scoring
+                .get_or_insert_with(|| Scoring::start(&this.childs));
+            match scoring.poll(cx) {
+                Poll::Ready(scores) => {
+                    let best = best_index(scores);
+                    let switch = match &this.running {
+                        None => true,
+                        Some((current, _)) => {
+                            let margin = this.switch_margin.unwrap_or_default();
+                            let current_score = scores[*current].unwrap_or(f32::MIN);
+                            best != *current
+                                && scores[best].unwrap_or(f32::MIN) > current_score + margin
+                        }
+                    };
+                    this.scoring.take();
+                    if switch {
+                        this.start_child(best);
+                    }
+                }
+                Poll::Pending if this.running.is_none() => return Poll::Pending,
+                // keep running current child while new scores is computing
+                Poll::Pending => {}
+            }
+        }
+
+        let (_, fut) = this.running.as_mut().unwrap();
+        fut.as_mut().poll(cx)
+    }
+}
+
+IMPLEMENT_INTO_COMPOSITE!(UtilitySelector);
@@ -0,0 +1,535 @@
+#![cfg(feature = "macros")]
+
+use bhv_async::prelude::*;
+
+fn patrol() -> Composite {
+    Action! { "patrol", || async { RunStatus::Success } }
+}
+
+#[tokio::test]
+async fn expression_children() {
+    let fail = Action! { || async { RunStatus::Failure } };
+    let subtree: Composite = Sequence! { patrol(), Inverter::new(fail.clone()) }.into();
+
+    let tree: Composite = Sequence! {
+        subtree.clone(),
+        Inverter::new(fail.clone()),
+        PrioritySelector! {
+            fail.clone(),
+            Decorator! { || true, Sequence! { patrol(), patrol() } },
+        },
+        Decorator! { || true, || async { RunStatus::Success } },
+        WeightedSelector! { 1 => patrol(), 0 => fail },
+    }
+    .into();
+    assert_eq!((tree.task_production)().await, RunStatus::Success);
+}
+
+#[tokio::test]
+async fn single_child_nodes() {
+    let tries = std::rc::Rc::new(std::cell::Cell::new(0));
+    let flaky = Action! {
+        "flaky",
+        move || {
+            let tries = tries.clone();
+            async move {
+                tries.set(tries.get() + 1);
+                match tries.get() {
+                    3 => RunStatus::Success,
+                    _ => RunStatus::Failure,
+                }
+            }
+        }
+    };
+
+    let tree: Composite = Sequence! {
+        UntilSuccess! { flaky },
+        Inverter! { || async { RunStatus::Failure } },
+        Inverter! { UntilFailure! { || async { RunStatus::Failure } } },
+        InterruptAction! { || true, patrol() },
+        Timeout! { "1s", patrol() },
+        Wait! { "1ms" },
+        Inverter! { Timeout! { 0.001, Wait! { std::time::Duration::from_secs(1) } } },
+        Timeout! { "1s", || async { RunStatus::Success } },
+    }
+    .into();
+    assert_eq!((tree.task_production)().await, RunStatus::Success);
+}
+
+async fn open_door() -> RunStatus {
+    RunStatus::Success
+}
+
+#[tokio::test]
+async fn tree_dsl() {
+    let tries = std::rc::Rc::new(std::cell::Cell::new(0));
+    let flaky = {
+        let tries = tries.clone();
+        move || {
+            tries.set(tries.get() + 1);
+            let status = match tries.get() {
+                2 => RunStatus::Success,
+                _ => RunStatus::Failure,
+            };
+            async move { status }
+        }
+    };
+    let fail = Action! { || async { RunStatus::Failure } };
+
+    let tree = tree! {
+        <Sequence name="root">
+            <Action name="open" do={open_door}/>
+            <Action do={async { RunStatus::Success }}/>
+            <UntilSuccess retry=4>
+                <Action do={flaky}/>
+            </UntilSuccess>
+            <Inverter>
+                <UntilSuccess retry={1}>{fail.clone()}</UntilSuccess>
+            </Inverter>
+            <Decorator condition={|| true}>
+                <Action do={|| async { RunStatus::Success }} timeout=1/>
+            </Decorator>
+            <Inverter>
+                <Timeout duration={std::time::Duration::from_millis(5)}>
+                    <Wait duration=10/>
+                </Timeout>
+            </Inverter>
+            <WeightedSelector seed=7>
+                <Inverter weight=0>{fail.clone()}</Inverter>
+                {patrol()}
+            </WeightedSelector>
+        </Sequence>
+    };
+    assert_eq!(tree.name, "root");
+    assert_eq!((tree.task_production)().await, RunStatus::Success);
+    assert_eq!(tries.get(), 2);
+}
+
+async fn something() {}
+
+#[tokio::test]
+async fn action_body_syntax() {
+    let log = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+    let ready = std::rc::Rc::new(std::cell::Cell::new(false));
+
+    let tree: Composite = Sequence! {
+        Action! { something().await; },
+        Action! {
+            "log",
+            clone log => {
+                something().await;
+                log.borrow_mut().push("body");
+                RunStatus::Success
+            }
+        },
+        Action!(clone log, ready => log.borrow_mut().push(if ready.get() { "ready" } else { "wait" });),
+        // loops and if without else have no value, still a success
+        Action! {
+            clone log => for _ in 0..1 {
+                something().await;
+                log.borrow_mut().push("for");
+            }
+        },
+        Action! {
+            let again = true;
+            something().await;
+            if again {
+                something().await;
+            }
+        },
+        Action! {
+            let mut count = 0;
+            while count < 2 {
+                count += 1;
+            }
+        },
+        // return anywhere in setup, tasks of different types
+        Action! {
+            move || {
+                let ready = ready.clone();
+                if !ready.get() {
+                    for _ in 0..2 {
+                        if ready.get() {
+                            return async { RunStatus::Failure };
+                        }
+                    }
+                    ready.set(true);
+                    return async move { RunStatus::Success };
+                }
+                match ready.get() {
+                    true => async { RunStatus::Success },
+                    false => async { RunStatus::Failure },
+                }
+            }
+        },
+    }
+    .into();
+    assert_eq!((tree.task_production)().await, RunStatus::Success);
+    assert_eq!((tree.task_production)().await, RunStatus::Success);
+    assert_eq!(
+        *log.borrow(),
+        ["body", "wait", "for", "body", "ready", "for"]
+    );
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Vec2(f32, f32);
+
+#[bhv_async::behavior]
+async fn move_to(target: Vec2, mut steps: u32) -> RunStatus {
+    while steps > 0 {
+        steps -= 1;
+        something().await;
+    }
+    match target {
+        Vec2(x, _) if x < 0.0 => RunStatus::Failure,
+        _ => RunStatus::Success,
+    }
+}
+
+#[behavior(name = "say")]
+async fn speak<T: std::fmt::Display + Clone + 'static>(line: T) -> RunStatus {
+    let _ = line.to_string();
+    RunStatus::Success
+}
+
+#[tokio::test]
+async fn behavior_fn() {
+    let node = move_to(Vec2(1.0, 2.0), 3);
+    assert_eq!(node.name, "move_to");
+    // parameters are cloned, node run again and again
+    assert_eq!((node.task_production)().await, RunStatus::Success);
+    assert_eq!((node.task_production)().await, RunStatus::Success);
+
+    let tree: Composite = Sequence! {
+        speak("hello"),
+        Inverter! { move_to(Vec2(-1.0, 0.0), 0) },
+    }
+    .into();
+    assert_eq!((tree.task_production)().await, RunStatus::Success);
+    assert_eq!(speak(String::from("hi")).name, "say");
+}
+
+/// Run child again until it success, at most `times`
+#[derive(Behavior)]
+#[bhv(name = "retry")]
+struct Retry {
+    times: u32,
+    #[bhv(child)]
+    child: Composite,
+    #[bhv(state)]
+    task: Option<BoxAction>,
+    #[bhv(state)]
+    tries: u32,
+}
+
+impl std::future::Future for Retry {
+    type Output = RunStatus;
+
+    fn poll(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Self::Output> {
+        loop {
+            let this = &mut *self;
+            let task = this.task.get_or_insert_with(|| this.child.start());
+            match std::task::ready!(task.as_mut().poll(cx)) {
+                RunStatus::Failure if this.tries + 1 < this.times => {
+                    this.tries += 1;
+                    this.task = None;
+                }
+                status => return std::task::Poll::Ready(status),
+            }
+        }
+    }
+}
+
+#[derive(Behavior)]
+struct All<T: Clone + 'static> {
+    #[allow(dead_code)]
+    tag: T,
+    #[bhv(children)]
+    childs: Vec<Composite>,
+}
+
+impl<T: Clone + 'static> std::future::Future for All<T> {
+    type Output = RunStatus;
+
+    fn poll(
+        self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Self::Output> {
+        std::task::Poll::Ready(RunStatus::Success)
+    }
+}
+
+/// Wait with a !Unpin state
+#[derive(Behavior)]
+struct Pause {
+    duration: std::time::Duration,
+    #[bhv(state)]
+    sleep: Option<tokio::time::Sleep>,
+}
+
+impl std::future::Future for Pause {
+    type Output = RunStatus;
+
+    fn poll(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Self::Output> {
+        let duration = self.duration;
+        // sleep is never moved out while pinned
+        let mut sleep = unsafe { self.map_unchecked_mut(|this| &mut this.sleep) };
+        if sleep.is_none() {
+            sleep.set(Some(tokio::time::sleep(duration)));
+        }
+        std::task::ready!(sleep.as_pin_mut().unwrap().poll(cx));
+        std::task::Poll::Ready(RunStatus::Success)
+    }
+}
+
+#[tokio::test]
+async fn derive_behavior() {
+    let runs = std::rc::Rc::new(std::cell::Cell::new(0));
+    let flaky = Action! {
+        clone runs => {
+            runs.set(runs.get() + 1);
+            match runs.get() % 3 {
+                0 => RunStatus::Success,
+                _ => RunStatus::Failure,
+            }
+        }
+    };
+    let retry = Retry {
+        times: 3,
+        child: flaky.clone(),
+        task: None,
+        tries: 0,
+    };
+    assert_eq!(Retry::NAME, "retry");
+    assert_eq!(retry.children().len(), 1);
+
+    let node: Composite = retry.into();
+    assert_eq!((node.name.as_str(), node.kind), ("retry", "Retry"));
+    assert!(node.reusable);
+    // state is reset between runs
+    let tree: Composite = Sequence! { node.clone(), node }.into();
+    assert_eq!((tree.task_production)().await, RunStatus::Success);
+    assert_eq!(runs.get(), 6);
+
+    let all = All {
+        tag: "tag",
+        childs: vec![flaky.clone(), flaky],
+    };
+    assert_eq!(All::<&str>::NAME, "All");
+    assert_eq!(all.children().len(), 2);
+    assert_eq!(
+        (Composite::from(all).task_production)().await,
+        RunStatus::Success
+    );
+
+    // reused task sleep again on each run
+    let pause: Composite = Pause {
+        duration: std::time::Duration::from_millis(5),
+        sleep: None,
+    }
+    .into();
+    let start = std::time::Instant::now();
+    assert_eq!(
+        UntilFailure::new(pause).with_max_runs(2).await,
+        RunStatus::Success
+    );
+    assert!(start.elapsed() >= std::time::Duration::from_millis(10));
+}
+
+#[test]
+fn source_location() {
+    let line = line!();
+    let patrol = Action! { || async { RunStatus::Success } };
+    let guard: Composite = Inverter! {
+        Action! { || async { RunStatus::Failure } }
+    }
+    .into();
+    let hand_written: Composite = Inverter::new(patrol.clone()).into();
+
+    assert_eq!(patrol.location.file(), file!());
+    assert_eq!(patrol.location.line(), line + 1);
+    assert_eq!(guard.location.line(), line + 5);
+    assert_eq!(hand_written.location.line(), line + 6);
+    assert_eq!(move_to(Vec2(0.0, 0.0), 0).location.line(), line!());
+
+    // variable child is named after it
+    let root = tree! { {patrol} };
+    assert_eq!(root.binding, Some("patrol"));
+    assert_eq!(
+        root.label().to_string(),
+        format!("Action [patrol] at {}:{}:18", file!(), line + 1)
+    );
+}
+
+#[tokio::test]
+async fn node_attributes() {
+    let runs = std::rc::Rc::new(std::cell::Cell::new(0));
+    let attack = Action! {
+        #[name = "attack", tags("combat", "melee"), description = "hit target"]
+        || async { RunStatus::Success }
+    };
+    assert_eq!(attack.name, "attack");
+    assert_eq!(attack.tags, ["combat", "melee"]);
+    assert_eq!(attack.description, Some("hit target"));
+
+    let flaky = Action! {
+        #[retry = 3]
+        clone runs => {
+            runs.set(runs.get() + 1);
+            match runs.get() {
+                3 => RunStatus::Success,
+                _ => RunStatus::Failure,
+            }
+        }
+    };
+    assert_eq!(flaky.kind, "UntilSuccess");
+
+    let tree: Composite = Sequence! {
+        #![name = "combat", timeout = "2s"]
+        attack,
+        flaky,
+        #[timeout = "10ms", invert] Wait::new(std::time::Duration::from_secs(1)),
+        #[repeat = 2, condition = || true] Action! { || async { RunStatus::Success } },
+        #[tags("never")] PrioritySelector! {
+            #[invert] Action! { || async { RunStatus::Success } },
+            Action! { #[name = "fallback"] || async { RunStatus::Success } },
+        },
+    };
+    assert_eq!(tree.kind, "Timeout");
+    assert_eq!((tree.task_production)().await, RunStatus::Success);
+    assert_eq!(runs.get(), 3);
+
+    // first run and 2 retries
+    runs.set(0);
+    let failing = Action! {
+        #[retry = 2]
+        clone runs => {
+            runs.set(runs.get() + 1);
+            RunStatus::Failure
+        }
+    };
+    assert_eq!((failing.task_production)().await, RunStatus::Failure);
+    assert_eq!(runs.get(), 3);
+    runs.set(0);
+    let once = Action! {
+        #[retry = 0]
+        clone runs => {
+            runs.set(runs.get() + 1);
+            RunStatus::Failure
+        }
+    };
+    assert_eq!((once.task_production)().await, RunStatus::Failure);
+    assert_eq!(runs.get(), 1);
+
+    // tree! count runs as node attributes do
+    runs.set(0);
+    let failing = tree! {
+        <UntilSuccess retry=2>{Action! { clone runs => {
+            runs.set(runs.get() + 1);
+            RunStatus::Failure
+        } }}</UntilSuccess>
+    };
+    assert_eq!((failing.task_production)().await, RunStatus::Failure);
+    assert_eq!(runs.get(), 3);
+    runs.set(0);
+    let repeated = tree! {
+        <UntilFailure repeat=2>{Action! { clone runs => runs.set(runs.get() + 1); }}</UntilFailure>
+    };
+    assert_eq!((repeated.task_production)().await, RunStatus::Success);
+    assert_eq!(runs.get(), 2);
+
+    // renamed optional child still fail selector
+    runs.set(0);
+    let selector: Composite = PrioritySelector! {
+        #[name = "optional"] DecoratorContinue! { || true, Action! { || async { RunStatus::Success } } },
+        Action! { clone runs => runs.set(runs.get() + 1); },
+    }
+    .into();
+    assert_eq!((selector.task_production)().await, RunStatus::Success);
+    assert_eq!(runs.get(), 1);
+}
+
+#[tokio::test]
+async fn conditional_children() {
+    let log = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+    let step = |name: &'static str| {
+        Action! {
+            #[name = name]
+            clone log => log.borrow_mut().push(name);
+        }
+    };
+    let waypoints = ["a", "b", "c"];
+    let verbose = false;
+
+    let tree: Composite = Sequence! {
+        step("start"),
+        #[cfg(not(test))]
+        step("release only"),
+        if verbose => step("verbose"),
+        if !verbose => #[retry = 2] step("quiet"),
+        ..waypoints.iter().map(|name| step(name)),
+        #[cfg(test)]
+        ..Vec::<Composite>::new(),
+        PrioritySelector! {
+            if verbose => step("never"),
+            #[cfg(test)] step("tested"),
+        },
+        WeightedSelector! {
+            #[cfg(not(test))] 1 => step("never"),
+            if !verbose => 1 => step("weighted"),
+        },
+    }
+    .into();
+    assert_eq!((tree.task_production)().await, RunStatus::Success);
+    assert_eq!(
+        *log.borrow(),
+        ["start", "quiet", "a", "b", "c", "tested", "weighted"]
+    );
+
+    // variables of user named like macro internals
+    log.borrow_mut().clear();
+    let childs = vec![step("x"), step("y")];
+    let child = step("z");
+    let tree: Composite = Sequence! {
+        if !childs.is_empty() => child,
+        ..childs,
+    }
+    .into();
+    assert_eq!((tree.task_production)().await, RunStatus::Success);
+    assert_eq!(*log.borrow(), ["z", "x", "y"]);
+}
+
+#[tokio::test]
+async fn seeded_random_groups() {
+    async fn run_order(seed: u64) -> Vec<&'static str> {
+        let log = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let step = |name: &'static str| Action! { clone log => log.borrow_mut().push(name); };
+        let tree: Composite = Sequence! {
+            RandomSequence! { #![seed = seed] step("a"), step("b"), step("c"), step("d") },
+            RandomSelector! {
+                #![rng = fastrand::Rng::with_seed(seed)]
+                step("e"),
+                step("f"),
+                step("g"),
+            },
+            WeightedSelector! { #![seed = seed, name = "pick"] 1 => step("h"), 1 => step("i") },
+        }
+        .into();
+        assert_eq!((tree.task_production)().await, RunStatus::Success);
+        let order = log.borrow().clone();
+        order
+    }
+
+    let first = run_order(7).await;
+    assert_eq!(first.len(), 6);
+    for _ in 0..5 {
+        assert_eq!(run_order(7).await, first);
+    }
+}
@@ -0,0 +1,9 @@
+#![cfg(feature = "macros")]
+
+/// Diagnostics of macros on bad input
+/// TRYBUILD=overwrite cargo test --test ui to update .stderr files
+#[test]
+fn macro_errors() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
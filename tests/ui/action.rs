@@ -0,0 +1,23 @@
+use bhv_async::prelude::*;
+
+fn main() {
+    let _not_async = Action! { || RunStatus::Success };
+    let _bare_return = Action! {
+        || {
+            return;
+        }
+    };
+    let _name = Action! { 42, || async { RunStatus::Success } };
+    let _nested_return = Action! {
+        || {
+            if true {
+                return;
+            }
+            async { RunStatus::Success }
+        }
+    };
+    let _captures = Action! { clone a, b { RunStatus::Success } };
+    let _unknown = Action! { #[retries = 3] || async { RunStatus::Success } };
+    let _duration = Action! { #[timeout = "2 days"] || async { RunStatus::Success } };
+    let _tags = Action! { #[tags = "combat"] || async { RunStatus::Success } };
+}
@@ -0,0 +1,22 @@
+use bhv_async::prelude::*;
+
+#[behavior]
+fn not_async() -> RunStatus {
+    RunStatus::Success
+}
+
+#[behavior]
+async fn no_status() {}
+
+#[behavior]
+async fn borrowed(name: &str) -> RunStatus {
+    let _ = name;
+    RunStatus::Success
+}
+
+#[behavior(label = "x")]
+async fn bad_argument() -> RunStatus {
+    RunStatus::Success
+}
+
+fn main() {}
@@ -0,0 +1,14 @@
+use bhv_async::prelude::*;
+
+fn main() {
+    let patrol = Action! { || async { RunStatus::Success } };
+
+    let _no_condition = Decorator! { patrol };
+    let _no_child = Decorator! { || true, };
+    let _too_many = DecoratorContinue! { || true, patrol, patrol };
+    let _two_childs = Inverter! { patrol, patrol };
+    let _no_child_interrupt = InterruptAction! { || true };
+    let _no_duration = Timeout! { patrol };
+    let _bad_duration = Wait! { "1 parsec" };
+    let _wait_child = Wait! { "1s", patrol };
+}
@@ -0,0 +1,17 @@
+use bhv_async::prelude::*;
+
+#[derive(Behavior)]
+struct Unit;
+
+#[derive(Behavior)]
+struct Unknown {
+    #[bhv(childs)]
+    childs: Vec<Composite>,
+}
+
+#[derive(Behavior)]
+enum Node {
+    A,
+}
+
+fn main() {}
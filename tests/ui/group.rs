@@ -0,0 +1,15 @@
+use bhv_async::prelude::*;
+
+fn main() {
+    let patrol = Action! { || async { RunStatus::Success } };
+
+    let _empty = Sequence! {};
+    let _literal = Sequence! { 1 };
+    let _missing_comma = PrioritySelector! { patrol patrol };
+    let _missing_arrow = WeightedSelector! { 1.0 patrol };
+    let _guard = Sequence! { if true patrol };
+    let _cfg_group = Sequence! { #![cfg(test)] patrol };
+    let _cfg_action = Action! { #[cfg(test)] || async { RunStatus::Success } };
+    let _seed_sequence = Sequence! { #![seed = 1] patrol.clone() };
+    let _seed_child = RandomSelector! { #[seed = 1] patrol };
+}
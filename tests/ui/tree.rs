@@ -0,0 +1,10 @@
+use bhv_async::prelude::*;
+
+fn main() {
+    let _unknown = tree! { <Sequense><Wait duration=1/></Sequense> };
+    let _mismatch = tree! { <Sequence><Wait duration=1/></Selector> };
+    let _attribute = tree! { <Inverter count=3><Wait duration=1/></Inverter> };
+    let _missing = tree! { <Decorator><Wait duration=1/></Decorator> };
+    let _childs = tree! { <Wait duration=1><Wait duration=1/></Wait> };
+    let _duration = tree! { <Wait duration="1 parsec"/> };
+}